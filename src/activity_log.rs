@@ -2,14 +2,15 @@ use std::rc::Rc;
 use std::time::SystemTime;
 
 use druid::widget::prelude::*;
-use druid::im::{Vector};
+use druid::im::{OrdSet, Vector};
 use druid::lens::{self, LensExt};
 
-use druid::widget::{CrossAxisAlignment, Controller, Flex, Label, List, Container, Painter};
+use druid::widget::{Button, CrossAxisAlignment, Controller, Either, Flex, Label, List, Container,
+                    Painter, TextBox};
 
 use druid::{
-    Data, PaintCtx, RenderContext, Env, Event, EventCtx, kurbo,
-    LifeCycle, Point, Widget, WidgetPod, WidgetExt};
+    Data, PaintCtx, RenderContext, Env, Event, EventCtx, kurbo, Lens,
+    LifeCycle, LocalizedString, Menu, MenuItem, Point, Widget, WidgetPod, WidgetExt};
 
 use druid::{Selector, Cursor};
 
@@ -25,8 +26,9 @@ use crate::common::*;
 #[derive(Clone, Data)]
 enum LogEdit {
     None,
-    Killed(Rc<DateTime<Utc>>),
-    Restored(Rc<DateTime<Utc>>),
+    Killed(RecordId),
+    Restored(RecordId),
+    Edited(RecordId),
 }
 
 type TimeRecordCtx = ((AppModel, LogEdit), TimeRecord);
@@ -34,12 +36,16 @@ type TimeRecordCtx = ((AppModel, LogEdit), TimeRecord);
 struct LogEntryController;
 
 impl LogEntryController {
-    const CMD_HOT: Selector<Rc<DateTime<Utc>>> = Selector::new("alog_entry_hot");
+    const CMD_HOT: Selector<RecordId> = Selector::new("alog_entry_hot");
     const CMD_COLD: Selector = Selector::new("alog_entry_cold");
 }
 
 fn format_time_record(task: &Task, record: &TimeRecord) -> String {
-    let name =  format!("{:wid$}", task.name, wid = 11);
+    let name = match record.kind {
+        RecordKind::Work => format!("{:wid$}", task.name, wid = 11),
+        // breaks and pauses don't count toward the task, show what they are
+        kind => format!("{:wid$}", format!("({})", kind.to_string().to_lowercase()), wid = 11),
+    };
 
     let duration = time::format_duration(
         &record.to.signed_duration_since(*record.from));
@@ -58,15 +64,418 @@ fn format_time_record(task: &Task, record: &TimeRecord) -> String {
     format!("{} {:<10} {:<10}", name, duration, time)
 }
 
+fn format_edit_time(ts: &DateTime<Utc>) -> String {
+    DateTime::<Local>::from(*ts).format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn begin_record_edit(model: &mut AppModel, record: &TimeRecord) {
+    model.record_edit = Some(RecordDraft {
+        kind: RecordDraftKind::Edit,
+        id: record.id,
+        start: format_edit_time(&record.from),
+        end: format_edit_time(&record.to),
+        task: model.tasks.get(&record.uid).map(|t| t.name.clone()).unwrap_or_default(),
+        error: "".to_string(),
+    });
+}
+
+fn begin_record_split(model: &mut AppModel, record: &TimeRecord) {
+    let middle = *record.from + record.to.signed_duration_since(*record.from) / 2;
+
+    model.record_edit = Some(RecordDraft {
+        kind: RecordDraftKind::Split,
+        id: record.id,
+        start: format_edit_time(&middle),
+        end: format_edit_time(&record.to),
+        task: model.tasks.get(&record.uid).map(|t| t.name.clone()).unwrap_or_default(),
+        error: "".to_string(),
+    });
+}
+
+/// Task typed in the record editor: its uid or its name. The record's own
+/// task `current` is kept while its name is unchanged, other names must be
+/// unique.
+fn find_task_uid(model: &AppModel, name: &str, current: &str) -> Result<String, String> {
+    let name = name.trim();
+
+    if let Some(task) = model.tasks.get(name) {
+        return Ok(task.uid.clone());
+    }
+
+    if model.tasks.get(current).map_or(false, |t| t.name.eq(name)) {
+        return Ok(current.to_string());
+    }
+
+    let found: Vec<&Task> = model.tasks.values()
+        .filter(|t| t.name.eq(name) && t.task_status != TaskStatus::Archived)
+        .collect();
+
+    match found.as_slice() {
+        [task] => Ok(task.uid.clone()),
+        [] => Err(format!("No task named '{}'", name)),
+        _ => Err(format!("More than one task named '{}', use the uid", name)),
+    }
+}
+
+fn validate_record_edit(model: &AppModel, old: &TimeRecord, draft: &RecordDraft)
+                        -> Result<TimeRecord, String>
+{
+    let base = DateTime::<Local>::from(*old.from);
+
+    let from = time::parse_local_time(&draft.start, &base)
+        .ok_or("Start should look like 2022-01-31 09:00 or 09:00")?;
+    let to = time::parse_local_time(&draft.end, &base)
+        .ok_or("End should look like 2022-01-31 10:00 or 10:00")?;
+
+    if to <= from {
+        return Err("Record should end after it starts".to_string());
+    }
+
+    if to > Utc::now() {
+        return Err("Record should not end in the future".to_string());
+    }
+
+    let uid = find_task_uid(model, &draft.task, &old.uid)?;
+
+    model.check_overlap(&from, &to, Some(old.id), Some(old.kind))?;
+
+    Ok(TimeRecord{id: old.id, from: Rc::new(from), to: Rc::new(to), uid, kind: old.kind,
+                  session_id: old.session_id})
+}
+
+/// Returns uids of the tasks whose sums changed.
+fn commit_record_edit(model: &mut AppModel, old: &TimeRecord, draft: &RecordDraft)
+                      -> Result<Vec<String>, String>
+{
+    let record = validate_record_edit(model, old, draft)?;
+
+    db::update_time_record(model.db.clone(), &record)
+        .map_err(|what| format!("db error: {}", what))?;
+
+    model.records.insert(record.id, record.clone());
+
+    Ok(vec![old.uid.clone(), record.uid])
+}
+
+fn commit_record_split(model: &mut AppModel, old: &TimeRecord, draft: &RecordDraft)
+                       -> Result<Vec<String>, String>
+{
+    let at = time::parse_local_time(&draft.start, &DateTime::<Local>::from(*old.from))
+        .ok_or("Split time should look like 2022-01-31 09:30 or 09:30")?;
+
+    let uid = find_task_uid(model, &draft.task, &old.uid)?;
+
+    let (first, mut second) = split_record(old, &at, &uid)
+        .ok_or("Split time should be inside the record")?;
+
+    second.id = db::split_time_record(model.db.clone(), &first, &second)
+        .map_err(|what| format!("db error: {}", what))?;
+
+    model.records.insert(first.id, first.clone());
+    model.records.insert(second.id, second.clone());
+
+    Ok(vec![first.uid, second.uid])
+}
+
+fn apply_record_edit(model: &mut AppModel) {
+    let mut draft = match model.record_edit.clone() {
+        Some(draft) => draft,
+        None => return,
+    };
+
+    let old = match model.records.get(&draft.id) {
+        Some(old) => old.clone(),
+        None => {
+            model.record_edit = None;
+            return;
+        }
+    };
+
+    let result = match draft.kind {
+        RecordDraftKind::Edit => commit_record_edit(model, &old, &draft),
+        RecordDraftKind::Split => commit_record_split(model, &old, &draft),
+    };
+
+    match result {
+        Ok(uids) => {
+            for uid in uids {
+                if model.tasks.contains_key(&uid) {
+                    model.rebuild_task_sum(&uid);
+                }
+            }
+
+            model.record_edit = None;
+        },
+        Err(what) => {
+            println!("record edit error: {}", what);
+            draft.error = what;
+            model.record_edit = Some(draft);
+        }
+    }
+}
+
+/// Merge records of one task into a single record of their summed time,
+/// refused if work of other tasks lies in between. Pauses and breaks in
+/// between move after the merged record.
+fn merge_selected_records(model: &mut AppModel, keys: &[RecordId]) {
+    let records: Vec<TimeRecord> = keys.iter()
+        .filter(|k| !model.records_killed.contains(*k))
+        .filter_map(|k| model.records.get(k).cloned())
+        .collect();
+
+    let (from, to) = match (records.iter().map(|r| *r.from).min(),
+                            records.iter().map(|r| *r.to).max()) {
+        (Some(from), Some(to)) if records.len() > 1 => (from, to),
+        _ => return,
+    };
+
+    let mut ignored = (*model.records_killed).clone();
+    for r in &records {
+        ignored.insert(r.id);
+    }
+
+    if find_overlapping(&model.records, &ignored, &from, &to, Some(RecordKind::Work)).is_some() {
+        println!("merge error: records of other tasks in between");
+        return;
+    }
+
+    let between: Vec<TimeRecord> = model.records.values()
+        .filter(|r| !ignored.contains(&r.id) && r.kind != RecordKind::Work)
+        .filter(|r| *r.from < to && *r.to > from)
+        .cloned()
+        .collect();
+
+    let (merged, moved) = match merge_records(&records, &between) {
+        Some(result) => result,
+        None => return,
+    };
+
+    let removed: Vec<RecordId> = records.iter()
+        .map(|r| r.id)
+        .filter(|id| *id != merged.id)
+        .collect();
+
+    if let Err(what) = db::merge_time_records(model.db.clone(), &merged, &removed, &moved) {
+        println!("db error: {}", what);
+        return;
+    }
+
+    for id in &removed {
+        model.records.remove(id);
+    }
+
+    for r in moved {
+        model.records.insert(r.id, r);
+    }
+
+    model.records.insert(merged.id, merged.clone());
+    model.rebuild_task_sum(&merged.uid);
+    model.records_selected = Rc::new(TimeRecordSet::new());
+}
+
+/// Move records to the task `uid`, killed records are left alone.
+fn reassign_records(model: &mut AppModel, keys: &[RecordId], uid: &String) {
+    let moved: Vec<TimeRecord> = keys.iter()
+        .filter(|k| !model.records_killed.contains(k))
+        .filter_map(|k| model.records.get(k).cloned())
+        .filter(|r| !r.uid.eq(uid))
+        .collect();
+
+    if moved.is_empty() {
+        return;
+    }
+
+    let moved_keys: Vec<RecordId> = moved.iter().map(|r| r.id).collect();
+
+    if let Err(what) = db::reassign_time_records(model.db.clone(), &moved_keys, uid) {
+        println!("db error: {}", what);
+        return;
+    }
+
+    let mut affected = OrdSet::unit(uid.clone());
+
+    for record in moved {
+        affected.insert(record.uid.clone());
+        model.records.insert(record.id, TimeRecord{uid: uid.clone(), ..record});
+    }
+
+    for task_uid in affected.iter() {
+        model.rebuild_task_sum(task_uid);
+    }
+
+    model.records_selected = Rc::new(TimeRecordSet::new());
+}
+
+fn make_record_menu(model: &AppModel, record: &TimeRecord) -> Menu<AppModel> {
+    let mut result = Menu::new(LocalizedString::new("Record"));
+
+    if model.records_killed.contains(&record.id) {
+        return result;
+    }
+
+    // act on the whole selection when the clicked record is part of it
+    let keys: Vec<RecordId> = if model.records_selected.contains(&record.id) {
+        model.records_selected.iter().cloned().collect()
+    } else {
+        vec![record.id]
+    };
+
+    let mut move_menu = Menu::new(LocalizedString::new("Move to task"));
+
+    let mut tasks: Vec<&Task> = model.tasks.values()
+        .filter(|t| t.task_status != TaskStatus::Archived)
+        .collect();
+    tasks.sort();
+
+    for task in tasks {
+        let keys = keys.clone();
+        let uid = task.uid.clone();
+        move_menu = move_menu.entry(
+            MenuItem::new(task.name.clone()).on_activate(
+                move |_ctx, data: &mut AppModel, _env| {
+                    reassign_records(data, &keys, &uid);
+                }));
+    }
+
+    let record_edit = record.clone();
+    let record_split = record.clone();
+
+    result = result
+        .entry(
+            MenuItem::new(LocalizedString::new("Edit")).on_activate(
+                move |_ctx, data: &mut AppModel, _env| {
+                    begin_record_edit(data, &record_edit);
+                }))
+        .entry(
+            MenuItem::new(LocalizedString::new("Split...")).on_activate(
+                move |_ctx, data: &mut AppModel, _env| {
+                    begin_record_split(data, &record_split);
+                }));
+
+    // pauses in between don't stop a merge
+    let next = records_by_time(&model.records)
+        .skip_while(|r| r.id != record.id)
+        .skip(1)
+        .find(|r| !model.records_killed.contains(&r.id) && r.kind == record.kind);
+
+    if keys.len() > 1 {
+        let same_task = keys.iter()
+            .filter_map(|k| model.records.get(k))
+            .all(|r| r.uid.eq(&record.uid));
+
+        if same_task {
+            result = result.entry(
+                MenuItem::new(LocalizedString::new("Merge selected")).on_activate(
+                    move |_ctx, data: &mut AppModel, _env| {
+                        merge_selected_records(data, &keys);
+                    }));
+        }
+    } else if let Some(next) = next.filter(|r| r.uid.eq(&record.uid)) {
+        let gap = next.from.signed_duration_since(*record.to);
+        let keys = vec![record.id, next.id];
+
+        // the pause is not worked time, it moves after the merged record
+        let title = if gap > chrono::Duration::zero() {
+            format!("Merge with next ({} pause moves after)", time::format_duration(&gap))
+        } else {
+            "Merge with next".to_string()
+        };
+
+        result = result.entry(
+            MenuItem::new(title).on_activate(
+                move |_ctx, data: &mut AppModel, _env| {
+                    merge_selected_records(data, &keys);
+                }));
+    }
+
+    result.entry(move_menu)
+}
+
+fn draft_lens(field: fn(&mut RecordDraft) -> &mut String) -> impl Lens<TimeRecordCtx, String>
+{
+    lens::Identity.map(
+        move |((model, _), _): &TimeRecordCtx| model.record_edit.clone()
+            .map(|mut d| field(&mut d).clone()).unwrap_or_default(),
+        move |((model, _), _): &mut TimeRecordCtx, value: String| {
+            if let Some(ref mut draft) = model.record_edit {
+                *field(draft) = value;
+            }
+        })
+}
+
+fn record_edit_widget() -> impl Widget<TimeRecordCtx> {
+    let edit_fields = Flex::row()
+        .with_child(TextBox::new().with_font(FONT_LOG_DESCR)
+                    .lens(draft_lens(|d| &mut d.task)).fix_width(120.0))
+        .with_default_spacer()
+        .with_child(TextBox::new().with_font(FONT_LOG_DESCR)
+                    .lens(draft_lens(|d| &mut d.start)).fix_width(160.0))
+        .with_default_spacer()
+        .with_child(TextBox::new().with_font(FONT_LOG_DESCR)
+                    .lens(draft_lens(|d| &mut d.end)).fix_width(160.0));
+
+    let split_fields = Flex::row()
+        .with_child(Label::new("split at").with_font(FONT_LOG_DESCR))
+        .with_default_spacer()
+        .with_child(TextBox::new().with_font(FONT_LOG_DESCR)
+                    .lens(draft_lens(|d| &mut d.start)).fix_width(160.0))
+        .with_default_spacer()
+        .with_child(Label::new("rest to").with_font(FONT_LOG_DESCR))
+        .with_default_spacer()
+        .with_child(TextBox::new().with_font(FONT_LOG_DESCR)
+                    .lens(draft_lens(|d| &mut d.task)).fix_width(120.0));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Either::new(|((model, _), _): &TimeRecordCtx, _env: &Env|
+                        model.record_edit.as_ref()
+                            .map_or(false, |d| d.kind == RecordDraftKind::Split),
+                        split_fields,
+                        edit_fields))
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(Button::new("Save")
+                            .on_click(|_ctx, ((_, action), record): &mut TimeRecordCtx, _env| {
+                                *action = LogEdit::Edited(record.id);
+                            }))
+                .with_default_spacer()
+                .with_child(Button::new("Cancel")
+                            .on_click(|_ctx, ((model, _), _): &mut TimeRecordCtx, _env| {
+                                model.record_edit = None;
+                            }))
+                .with_default_spacer()
+                .with_child(Label::new(|((model, _), _): &TimeRecordCtx, _env: &_| {
+                    model.record_edit.as_ref().map(|d| d.error.clone()).unwrap_or_default()
+                }).with_text_color(DELETING_TASK_BORDER)))
+        .padding(6.0)
+}
+
 impl<W: Widget<TimeRecordCtx>> Controller<TimeRecordCtx, W> for LogEntryController {
     fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event,
         data: &mut TimeRecordCtx, env: &Env,)
     {
         match event {
+            Event::MouseDown(ref mouse) if mouse.button.is_right() => {
+                ctx.show_context_menu(make_record_menu(&(data.0).0, &data.1), mouse.pos);
+            },
+            // ctrl + click selects records instead of killing them
+            Event::MouseDown(ref mouse) if mouse.button.is_left() && mouse.mods.ctrl() => {
+                let ((model, _), record) = data;
+
+                model.records_selected = Rc::new(
+                    if model.records_selected.contains(&record.id) {
+                        model.records_selected.without(&record.id)
+                    } else {
+                        model.records_selected.update(record.id)
+                    });
+            },
+            Event::MouseUp(ref mouse) if mouse.mods.ctrl() => {},
             // Event::Command(cmd) if cmd.is(LogEntryController::CMD_HOT) => {
             //     let value = cmd.get(LogEntryController::CMD_HOT).unwrap();
 
-            //     if (*value).eq(&data.1.from) {
+            //     if (*value).eq(&data.1.id) {
             //         ctx.set_handled();
             //     }
             // },
@@ -81,7 +490,7 @@ impl<W: Widget<TimeRecordCtx>> Controller<TimeRecordCtx, W> for LogEntryControll
     {
         match event {
             // LifeCycle::HotChanged(value) => if *value {
-            //     ctx.submit_command(LogEntryController::CMD_HOT.with(data.1.from.clone()));
+            //     ctx.submit_command(LogEntryController::CMD_HOT.with(data.1.id));
             // },
 
             _ => child.lifecycle(ctx, event, data, env),
@@ -91,7 +500,7 @@ impl<W: Widget<TimeRecordCtx>> Controller<TimeRecordCtx, W> for LogEntryControll
 
 pub struct ActivityLogWidget {
     inner: WidgetPod<AppModel, Container<AppModel>>,
-    hot: Option<Rc<DateTime<Utc>>>,
+    hot: Option<RecordId>,
 }
 
 impl ActivityLogWidget {
@@ -101,57 +510,77 @@ impl ActivityLogWidget {
 
             .with_child(
                     List::new(||{
-                        Label::new(|((model, _killed), record): &TimeRecordCtx, _env: &_| {
+                        let view = Label::new(|((model, _killed), record): &TimeRecordCtx, _env: &_| {
                             if let Some(task) = model.tasks.get(&record.uid) {
                                 format_time_record(&task, &record)
                             } else {
                                 "".to_string()
                             }
                         })
-                        .with_font(FONT_LOG_DESCR.clone())
+                        .with_font(FONT_LOG_DESCR)
 
                         .padding(6.0)
-                        .controller(LogEntryController)
                         .on_click(|_ctx, ((data, action), what): &mut TimeRecordCtx, _env| {
-                            if data.records_killed.contains(&what.from) {
-                                *action = LogEdit::Restored(what.from.clone());
+                            if data.records_killed.contains(&what.id) {
+                                *action = LogEdit::Restored(what.id);
                             } else {
-                                *action = LogEdit::Killed(what.from.clone());
+                                *action = LogEdit::Killed(what.id);
                             }
                         })
+                        .controller(LogEntryController)
                         .background(
-                            Painter::new(|ctx: &mut PaintCtx, ((model, _), record): &TimeRecordCtx, _env| {
+                            Painter::new(|ctx: &mut PaintCtx, ((model, _), record): &TimeRecordCtx, env| {
                                 let bounds = ctx.size().to_rect();
 
+                                if model.records_selected.contains(&record.id) {
+                                    ctx.fill(bounds, &env.get(TASK_COLOR_BG));
+                                }
+
                                 let line =kurbo::Line::new(Point::new(bounds.min_x(), bounds.center().y), 
                                                            Point::new(bounds.max_x(), bounds.center().y));
                                 
-                                match (model.records_killed.contains(&record.from), ctx.is_hot()) {
-                                    (true, false) => ctx.stroke(line.clone(), &COLOR_ACTIVE, 2.0),
-                                    (true, true) => ctx.stroke(line.clone(), &RESTORED_TASK_BORDER, 2.0),
-                                    (false, true) => ctx.stroke(line.clone(), &DELETING_TASK_BORDER, 2.0),
+                                match (model.records_killed.contains(&record.id), ctx.is_hot()) {
+                                    (true, false) => ctx.stroke(line.clone(), &env.get(COLOR_ACTIVE), 2.0),
+                                    (true, true) => ctx.stroke(line.clone(), &env.get(RESTORED_TASK_BORDER), 2.0),
+                                    (false, true) => ctx.stroke(line.clone(), &env.get(DELETING_TASK_BORDER), 2.0),
                                     _ => {},
                                 }
-                            }))
+                            }));
+
+                        Either::new(|((model, _), record): &TimeRecordCtx, _env: &Env|
+                                    model.record_edit.as_ref()
+                                        .map_or(false, |d| d.id == record.id),
+                                    record_edit_widget(),
+                                    view)
                     })
             .padding((0.0, 0.0, 15.0, 0.0))
             .lens(lens::Identity.map(
                 |m: &AppModel| ((m.clone(),
                                 LogEdit::None),
-                                m.records.values().map(|v| v.clone()).rev().collect()),
+                                records_by_time(&m.records)
+                                    .map(|v| v.clone()).rev().collect()),
 
-                |outer: &mut AppModel, ((_inner, action), _) : ((AppModel, LogEdit), Vector<TimeRecord>)|
+                |outer: &mut AppModel, ((inner, action), _) : ((AppModel, LogEdit), Vector<TimeRecord>)|
                 {
+                      if !outer.record_edit.same(&inner.record_edit) {
+                          outer.record_edit = inner.record_edit.clone();
+                      }
+
+                      if !outer.records_selected.same(&inner.records_selected) {
+                          outer.records_selected = inner.records_selected.clone();
+                      }
+
                       match action {
                         LogEdit::None => {},
-                        LogEdit::Killed(ref ts) => {
-                            if let Some(rec) = outer.records.get(&ts) {
+                        LogEdit::Edited(_) => apply_record_edit(outer),
+                        LogEdit::Killed(id) => {
+                            if let Some(rec) = outer.records.get(&id) {
                                 if let Err(what) = db::remove_time_record(outer.db.clone(), &rec) {
                                     println!("db error: {}", what);                                
                                 }
                             }
 
-                            outer.records_killed = Rc::new(outer.records_killed.update(*ts.clone()));
+                            outer.records_killed = Rc::new(outer.records_killed.update(id));
                             let mut sums = TaskSums::new();
                             
                             for (uid, _) in &outer.tasks {
@@ -162,14 +591,14 @@ impl ActivityLogWidget {
                             
                             outer.task_sums = sums;
                         },
-                        LogEdit::Restored(ref ts) => {
-                            if let Some(rec) = outer.records.get(&ts) {
-                                if let Err(what) = db::add_time_record(outer.db.clone(), &rec) {
+                        LogEdit::Restored(id) => {
+                            if let Some(rec) = outer.records.get(&id) {
+                                if let Err(what) = db::restore_time_record(outer.db.clone(), &rec) {
                                     println!("db error: {}", what);
                                 }
                             }
 
-                            outer.records_killed = Rc::new(outer.records_killed.without(ts));                            
+                            outer.records_killed = Rc::new(outer.records_killed.without(&id));                            
                             let mut sums = TaskSums::new();
                             
                             for (uid, _) in &outer.tasks {
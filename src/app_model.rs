@@ -1,13 +1,19 @@
 use druid::im::{OrdSet, Vector};
 
-use druid::{Data, TimerToken, Lens };
+use druid::{Data, ExtEventSink, TimerToken, Lens, WindowId };
 
 use chrono::prelude::*;
 use std::rc::Rc;
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
 
 use crate::task::*;
+use crate::time;
+use crate::config::Config;
+use crate::idle::IdleSource;
+use crate::sound::AudioController;
 
-#[derive(Debug, Clone, Data)]
+#[derive(Debug, Clone, Data, Serialize, Deserialize)]
 pub enum TrackingState {
     Inactive,
     Active(String),
@@ -15,6 +21,34 @@ pub enum TrackingState {
     Break(String)
 }
 
+impl TrackingState {
+    pub fn uid(&self) -> Option<&String> {
+        match self {
+            TrackingState::Active(uid) | TrackingState::Paused(uid) |
+            TrackingState::Break(uid) => Some(uid),
+            TrackingState::Inactive => None,
+        }
+    }
+}
+
+/// Questions that need an answer from the user before netupi can go on,
+/// shown one at a time above the main window content.
+#[derive(Debug, Clone, Data)]
+pub enum Prompt {
+    /// Time record left open by a session that never stopped, `to` is
+    /// the last checkpoint
+    OpenRecord(TimeRecord),
+    /// Pomodoro of the task finished, take the break or skip it
+    ConfirmBreak(String),
+    /// User was away during the work record `id` from `from` to `to`: keep
+    /// that time, discard it or give it to another task
+    Idle(TimeRecord),
+    /// Wall clock jumped from `from` to `to`, the session of `uid` was cut
+    /// at `from`: a work session is paused there with the pause record
+    /// `id`, a break ended there
+    ClockJump(TimeRecord),
+}
+
 #[derive(Debug, Clone, PartialEq, Data)]
 pub enum FocusFilter {
     Status(TaskStatus),
@@ -36,6 +70,45 @@ pub struct TrackingCtx {
     pub timestamp: Rc<DateTime<Utc>>,
     pub timer_id: Rc<TimerToken>,
     pub elapsed: Rc<chrono::Duration>,
+    /// open record of the running session
+    pub record_id: Option<RecordId>,
+    /// pomodoro the running work or pause belongs to
+    pub session_id: Option<SessionId>,
+    /// pomodoros completed since the last long break
+    pub cycle: u32,
+    /// pomodoros completed since tracking was last started by hand
+    pub auto_run: u32,
+    /// time added to the work interval of the running pomodoro
+    pub extended: Rc<chrono::Duration>,
+}
+
+/// Input of the "Add time entry" dialog, kept as typed until validated.
+#[derive(Debug, Clone, Data, Lens, Default)]
+pub struct TimeEntryDraft {
+    pub uid: Option<String>,
+    pub start: String,
+    pub end: String,
+    pub duration: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Data)]
+pub enum RecordDraftKind {
+    /// change start, end and task
+    Edit,
+    /// cut at `start`, the second part goes to `task`
+    Split,
+}
+
+/// Inline edit of the activity log record `id`.
+#[derive(Debug, Clone, Data, Lens)]
+pub struct RecordDraft {
+    pub kind: RecordDraftKind,
+    pub id: RecordId,
+    pub start: String,
+    pub end: String,
+    pub task: String,
+    pub error: String,
 }
 
 #[derive(Clone, Data, Lens)]
@@ -44,28 +117,176 @@ pub struct AppModel {
     pub tasks: TaskMap,
     pub records: TimeRecordMap,
     pub records_killed: Rc<TimeRecordSet>,
+    pub records_selected: Rc<TimeRecordSet>,
     pub task_sums: TaskSums,
+    pub completed_sessions: SessionCounts,
     pub tags: OrdSet<String>,
     pub tracking: TrackingCtx,
     pub selected_task: Option<String>,
     pub focus_filter: FocusFilter,
     pub tag_filter: Option<String>,
-    pub hot_log_entry: Option<Rc<DateTime<Utc>>>,
+    pub hot_log_entry: Option<RecordId>,
+    pub prompts: Vector<Prompt>,
+    pub time_entry: TimeEntryDraft,
+    pub record_edit: Option<RecordDraft>,
+    pub config: Config,
+    /// copy of `config` edited in the settings window, applied on Save
+    pub settings: Config,
+    pub config_path: Rc<PathBuf>,
+    pub idle: Option<Rc<dyn IdleSource>>,
+    pub audio: Rc<AudioController>,
+    /// routes clicks on notification buttons back as commands, set on launch
+    pub event_sink: Option<Rc<ExtEventSink>>,
+    /// typed in the break overlay to confirm skipping the break
+    pub break_skip_text: String,
+    /// mini timer window, if open
+    pub mini_window: Option<Rc<WindowId>>,
 
     pub show_task_edit: bool,
     pub show_task_summary: bool
 }
 
+/// Whether the next pomodoro can start on its own after a break or a
+/// skipped break.
+pub fn can_auto_start(model: &AppModel) -> bool {
+    model.config.auto_start_work &&
+        (model.config.auto_cycles == 0 || model.tracking.auto_run < model.config.auto_cycles)
+}
+
+/// Tracked without work interval, break or sound.
+pub fn is_stopwatch(model: &AppModel, uid: &String) -> bool {
+    model.config.stopwatch || model.tasks.get(uid).map_or(false, |t| t.stopwatch)
+}
+
 pub fn get_work_interval(model: &AppModel, uid: &String) -> chrono::Duration {
+    if model.tracking.state.uid() == Some(uid) {
+        return *model.tasks.get(uid).unwrap().work_duration.clone() + *model.tracking.extended;
+    }
+
     *model.tasks.get(uid).unwrap().work_duration.clone()
     // chrono::Duration::seconds(10)
 }
 
+/// The break after the last pomodoro of a cycle is a long one.
+pub fn is_long_break_due(model: &AppModel) -> bool {
+    model.config.long_break_cycle > 0 && model.tracking.cycle >= model.config.long_break_cycle
+}
+
 pub fn get_rest_interval(model: &AppModel, uid: &String) -> chrono::Duration {
+    if is_long_break_due(model) {
+        return chrono::Duration::minutes(model.config.long_break_minutes);
+    }
+
     *model.tasks.get(uid).unwrap().break_duration.clone()
     // chrono::Duration::seconds(10)
 }
 
+/// Work time past the work interval of the running or paused pomodoro.
+pub fn get_overtime(model: &AppModel) -> Option<chrono::Duration> {
+    let (uid, worked) = match model.tracking.state {
+        TrackingState::Active(ref uid) =>
+            (uid, *model.tracking.elapsed +
+             Utc::now().signed_duration_since(*model.tracking.timestamp)),
+        TrackingState::Paused(ref uid) => (uid, *model.tracking.elapsed),
+        _ => return None,
+    };
+
+    if is_stopwatch(model, uid) {
+        return None;
+    }
+
+    Some(worked - get_work_interval(model, uid)).filter(|d| *d > chrono::Duration::zero())
+}
+
+/// Time left until the work or break timer fires, `None` if no timer runs
+/// in the current state.
+pub fn get_remaining_time(model: &AppModel) -> Option<chrono::Duration> {
+    let since = Utc::now().signed_duration_since(*model.tracking.timestamp);
+
+    match model.tracking.state {
+        TrackingState::Active(ref uid) if is_stopwatch(model, uid) => None,
+        TrackingState::Active(ref uid) =>
+            Some(get_work_interval(model, uid) - *model.tracking.elapsed - since),
+        TrackingState::Break(ref uid) =>
+            Some(get_rest_interval(model, uid) - since),
+        _ => None,
+    }
+}
+
+/// Position in the long break cycle, "2/4" while the second pomodoro runs or
+/// during the break after it.
+pub fn get_cycle_string(d: &AppModel) -> String {
+    if d.config.long_break_cycle == 0 {
+        return "".to_string();
+    }
+
+    let pos = match d.tracking.state {
+        TrackingState::Break(_) => d.tracking.cycle,
+        _ => d.tracking.cycle + 1,
+    };
+
+    format!(" | {}/{}", pos.min(d.config.long_break_cycle), d.config.long_break_cycle)
+}
+
+/// One line about the running session, for the status bar and the mini
+/// window.
+pub fn get_status_string(d: &AppModel) -> String {
+    let status = match d.tracking.state {
+        TrackingState::Active(ref uid) => {
+            let active_task = &d.tasks.get(uid).expect("unknown uid");
+
+            let duration = d.tracking.elapsed.checked_add(&Utc::now()
+                .signed_duration_since(d.tracking.timestamp.as_ref().clone()))
+                .unwrap_or(chrono::Duration::zero());
+
+            if is_stopwatch(d, uid) {
+                return format!("Active: '{}' | Elapsed: {}",
+                               active_task.name, time::format_duration(&duration));
+            }
+
+            let total = get_work_interval(d, uid);
+
+            if let Some(overtime) = get_overtime(d) {
+                return format!("Overtime: '{}' | Elapsed: {} / {} (+{})",
+                               active_task.name, time::format_duration(&duration),
+                               time::format_duration(&total), time::format_duration(&overtime))
+                    + &get_cycle_string(d);
+            }
+
+            format!("Active: '{}' | Elapsed: {} / {}",
+                    active_task.name, time::format_duration(&duration), time::format_duration(&total))
+        },
+        TrackingState::Break(ref uid) => {
+            let rest_task = &d.tasks.get(uid).expect("unknown uid");
+
+            let duration =
+                Utc::now().signed_duration_since(d.tracking.timestamp.as_ref().clone());
+
+            let total = get_rest_interval(d, uid);
+
+            format!("{}: '{}' | Elapsed: {} / {}",
+                    if is_long_break_due(d) {"Long break"} else {"Break"}, rest_task.name, time::format_duration(&duration), time::format_duration(&total))
+        },
+        TrackingState::Paused(ref uid) => {
+            let active_task = &d.tasks.get(uid).expect("unknown uid");
+
+            if is_stopwatch(d, uid) {
+                return format!("Paused: '{}' | Elapsed: {}",
+                               active_task.name, time::format_duration(&d.tracking.elapsed));
+            }
+
+            format!("Paused: '{}' | Elapsed: {} / {}",
+                    active_task.name,
+                    time::format_duration(&d.tracking.elapsed),
+                    time::format_duration(&get_work_interval(d, uid)))
+        },
+
+        _ => return format!("")
+    };
+
+    status + &get_cycle_string(d)
+}
+
 impl AppModel {
     pub fn get_task(&self, uid_opt: &Option<String>) -> Option<&Task> {
         if let Some(uid) = uid_opt {
@@ -134,6 +355,39 @@ impl AppModel {
         return result;
     }
 
+    /// Check that `[from, to)` is free: no other record of `kind` (any kind
+    /// for `None`) and no running session in it. `except` is the record
+    /// being changed, if any.
+    pub fn check_overlap(&self, from: &DateTime<Utc>, to: &DateTime<Utc>,
+                         except: Option<RecordId>, kind: Option<RecordKind>)
+                         -> Result<(), String> {
+        let ignored = match except {
+            Some(id) => self.records_killed.update(id),
+            None => (*self.records_killed).clone(),
+        };
+
+        if let Some(other) = find_overlapping(&self.records, &ignored, from, to, kind) {
+            let name = self.tasks.get(&other.uid).map(|t| t.name.clone()).unwrap_or_default();
+            return Err(format!("Overlaps with '{}' at {}", name,
+                               DateTime::<Local>::from(*other.from).format("%d %b, %H:%M")));
+        }
+
+        if let TrackingState::Active(ref active) = self.tracking.state {
+            if *self.tracking.timestamp < *to {
+                return Err(format!("Overlaps with the running session of '{}'",
+                                   self.tasks.get(active).unwrap().name));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn rebuild_task_sum(&mut self, uid: &String) {
+        let sum = build_time_prefix_sum(&self.tasks, &self.records, uid.clone(),
+                                        &self.records_killed);
+        self.task_sums.insert(uid.clone(), sum);
+    }
+
     pub fn update_tags(&mut self) {
         self.tags.clear();
         self.tags = self.get_tags();
@@ -10,6 +10,7 @@ use std::env;
 // use netupi::task::*;
 use netupi::utils::*;
 use netupi::db;
+use netupi::config;
 
 pub fn main() -> anyhow::Result<()>{
     let args: Vec<String> = env::args().collect();
@@ -21,6 +22,8 @@ pub fn main() -> anyhow::Result<()>{
     let mut default_config_dir = dirs::config_dir().unwrap_or(PathBuf::new());
     default_config_dir.push("netupi");
 
+    let config = config::load(&config::default_path(&default_config_dir))?;
+
     let conn = db::init(default_config_dir)?;
     let db = Rc::new(conn);
 
@@ -29,7 +32,7 @@ pub fn main() -> anyhow::Result<()>{
     //     &DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc),
     //     &DateTime::from(SystemTime::now()))?;
 
-    let (imported_tasks, imported_records) = get_csv_entries(&args[1], &tasks)
+    let (imported_tasks, imported_records) = get_csv_entries(&args[1], &tasks, &config)
         .with_context(|| format!("Importing '{}' failed", &args[1]))?;
 
     for (_, task) in imported_tasks {
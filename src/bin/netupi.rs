@@ -13,7 +13,7 @@ use druid::widget::{CrossAxisAlignment, Flex, Label, SizedBox, List, Scroll, Con
 use druid::{
     AppLauncher, Application, Data, PaintCtx, RenderContext, Env, Event, EventCtx,
     LifeCycle, Point,
-    Menu, MenuItem, TimerToken, KeyOrValue,
+    Menu, MenuItem, TimerToken,
     LocalizedString, UnitPoint, Widget, WidgetPod, WidgetExt, WindowDesc, WindowId};
 
 use chrono::prelude::*;
@@ -23,10 +23,17 @@ use clap::Parser;
 use netupi::maybe::Maybe;
 use netupi::task::*;
 use netupi::db;
+use netupi::config::{self, Config};
+use netupi::idle;
+#[cfg(unix)]
+use netupi::ipc;
+use netupi::theme;
+use netupi::sound::AudioController;
 use netupi::app_model::*;
 use netupi::task_list::*;
 use netupi::task_details::*;
 use netupi::activity_log::*;
+use netupi::prompt::*;
 use netupi::common::*;
 use netupi::time;
 use netupi::widgets;
@@ -36,6 +43,10 @@ use netupi::widgets;
 struct Args {
     #[clap(short, long)]
     config_dir: Option<PathBuf>,
+    #[clap(long)]
+    config: Option<PathBuf>,
+    #[clap(long)]
+    socket: Option<PathBuf>,
 }
 
 fn get_db_path(args: &Args) -> PathBuf {
@@ -44,22 +55,69 @@ fn get_db_path(args: &Args) -> PathBuf {
     args.config_dir.clone().unwrap_or(default_config_dir)
 }
 
-fn get_last_task(tasks: &TaskMap, records: &TimeRecordMap) -> Option<String>
-{
-    for r in records.iter().rev() {
-        if let Some(t) = tasks.get(&r.1.uid) {
-            if t.task_status != TaskStatus::Archived {
-                return Some(t.uid.clone());
+/// Resume the session saved by the previous run if it can still go on:
+/// a running pomodoro needs its open record and time left on the timer.
+fn restore_tracking(data: &mut AppModel, saved: TrackingCtx) {
+    // the cycle goes on after a restart between pomodoros, the time since
+    // the last work comes from the records
+    data.tracking.cycle = saved.cycle;
+
+    let uid = match saved.state.uid() {
+        Some(uid) if data.tasks.contains_key(uid) => uid.clone(),
+        _ => return,
+    };
+
+    let open_idx = data.prompts.iter().position(|p| matches!(p,
+        Prompt::OpenRecord(r) if r.uid.eq(&uid) && Some(r.id) == saved.record_id));
+
+    data.tracking = saved;
+
+    let resumable = match data.tracking.state {
+        TrackingState::Active(_) => open_idx.is_some(),
+        _ => true,
+    } && (get_remaining_time(data).map_or(true, |rem| rem > chrono::Duration::zero()) ||
+          data.config.flow_mode && matches!(data.tracking.state, TrackingState::Active(_)));
+
+    if !resumable {
+        // the open record, if any, is left to the user, but the pomodoro
+        // is over
+        if let Some(id) = data.tracking.session_id.take() {
+            if let Err(what) = db::close_session(data.db.clone(), id, &data.tracking.timestamp,
+                                                 SessionOutcome::Stopped) {
+                println!("db error: {}", what);
             }
         }
+
+        data.tracking.state = TrackingState::Inactive;
+        data.tracking.record_id = None;
+        return;
     }
 
-    return None;
+    if let Some(idx) = open_idx {
+        data.prompts.remove(idx);
+    } else {
+        data.tracking.record_id = None;
+    }
 }
 
 pub fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
+    let config_path = args.config.clone()
+        .unwrap_or_else(|| config::default_path(&get_db_path(&args)));
+
+    let config = config::load(&config_path).unwrap_or_else(|what| {
+        println!("config error: {}, using defaults", what);
+        Config::default()
+    });
+
+    let idle = if config.idle_minutes > 0 {
+        idle::make_source(&config.idle_source, &get_db_path(&args), IDLE_POLL_INTERVAL)
+            .map(Rc::from)
+    } else {
+        None
+    };
+
     let conn = db::init(get_db_path(&args))?;
     let db = Rc::new(conn);
 
@@ -76,6 +134,10 @@ pub fn main() -> anyhow::Result<()> {
     }
 
     let last_task = get_last_task(&tasks, &records);
+    let completed_sessions = db::get_completed_sessions(db.clone())?;
+
+    let prompts = db::get_open_time_records(db.clone())?
+        .into_iter().map(Prompt::OpenRecord).collect();
 
     let filter = if let Some(ref uid) = last_task {
         FocusFilter::Status(tasks.get(uid).unwrap().task_status.clone())
@@ -88,18 +150,36 @@ pub fn main() -> anyhow::Result<()> {
         tasks,
         records,
         records_killed: Rc::new(TimeRecordSet::new()),
+        records_selected: Rc::new(TimeRecordSet::new()),
         task_sums,
+        completed_sessions,
         tags,
         tracking: TrackingCtx{state: TrackingState::Inactive,
                               timestamp: Rc::new(Utc::now()),
                               timer_id: Rc::new(TimerToken::INVALID),
-                              elapsed: Rc::new(chrono::Duration::zero())},
+                              elapsed: Rc::new(chrono::Duration::zero()),
+                              record_id: None,
+                              session_id: None,
+                              cycle: 0,
+                              auto_run: 0,
+                              extended: Rc::new(chrono::Duration::zero())},
 
         // todo make selected_task Option
         selected_task: last_task,
         focus_filter: filter,
         tag_filter: None,
         hot_log_entry: None,
+        prompts,
+        time_entry: TimeEntryDraft::default(),
+        record_edit: None,
+        settings: config.clone(),
+        config,
+        config_path: Rc::new(config_path),
+        idle,
+        audio: Rc::new(AudioController::new()),
+        event_sink: None,
+        break_skip_text: String::new(),
+        mini_window: None,
         show_task_edit: false,
         show_task_summary: true,
     };
@@ -107,12 +187,31 @@ pub fn main() -> anyhow::Result<()> {
     // TODO should be done in ctor
     data.update_tags();
 
-    let main_window = WindowDesc::new(ui_builder())
+    if let Some(saved) = db::get_tracking(data.db.clone())? {
+        restore_tracking(&mut data, saved);
+    }
+
+    let main_window = WindowDesc::new(theme::themed(ui_builder()))
         .window_size((1200.0, 800.0))
         .menu(make_menu)
         .title(LocalizedString::new("netupi-window-title").with_placeholder("netupi"));
 
-    AppLauncher::with_window(main_window)
+    let launcher = AppLauncher::with_window(main_window);
+
+    data.event_sink = Some(Rc::new(launcher.get_external_handle()));
+
+    // the socket is removed when the server goes out of scope after launch
+    #[cfg(unix)]
+    let _server = {
+        let socket_path = args.socket.clone()
+            .unwrap_or_else(|| ipc::default_path(&get_db_path(&args)));
+
+        ipc::serve(socket_path, launcher.get_external_handle())
+            .map_err(|what| println!("ipc error: {}", what))
+            .ok()
+    };
+
+    launcher
         .log_to_console()
         .launch(data)
         .expect("launch failed");
@@ -128,10 +227,30 @@ fn make_menu(_: Option<WindowId>, model: &AppModel, _: &Env) -> Menu<AppModel> {
 
     let mut file = Menu::new(LocalizedString::new("File"));
 
+    file = file.entry(
+        MenuItem::new(LocalizedString::new("Settings..."))
+            .on_activate(move |ctx, _data, _env| {ctx.submit_command(COMMAND_SETTINGS_OPEN.with(()));})
+    );
+
+    file = file.entry(
+        MenuItem::new(LocalizedString::new(if model.mini_window.is_some() {"Hide mini timer"} else {"Mini timer"}))
+            .on_activate(move |ctx, _data, _env| {ctx.submit_command(COMMAND_MINI_WINDOW.with(()));})
+    );
+
+    file = file.entry(
+        MenuItem::new(LocalizedString::new(if model.config.sound {"Mute sounds"} else {"Unmute sounds"}))
+            .on_activate(move |ctx, _data, _env| {ctx.submit_command(COMMAND_SOUND_MUTE.with(()));})
+    );
+
     file = file.entry(
         MenuItem::new(LocalizedString::new("Exit"))
             .on_activate(move |_ctx, _data, _env| {Application::global().quit();})
     );
+
+    file = file.rebuild_on(|prev: &AppModel, now: &AppModel, _env: &Env| {
+        prev.config.sound != now.config.sound ||
+            prev.mini_window.is_some() != now.mini_window.is_some()
+    });
     
     let mut task = make_task_menu(model, &model.selected_task);
     task = task.rebuild_on(|prev: &AppModel, now: &AppModel, _env: &Env| {
@@ -149,45 +268,6 @@ struct StatusBar {
     timer_id: TimerToken,
 }
 
-fn get_status_string(d: &AppModel) -> String {
-    match d.tracking.state {
-        TrackingState::Active(ref uid) => {
-            let active_task = &d.tasks.get(uid).expect("unknown uid");
-
-            let duration = d.tracking.elapsed.checked_add(&Utc::now()
-                .signed_duration_since(d.tracking.timestamp.as_ref().clone()))
-                .unwrap_or(chrono::Duration::zero());
-
-            let total = get_work_interval(d, uid);
-
-            format!("Active: '{}' | Elapsed: {} / {}",
-                    active_task.name, time::format_duration(&duration), time::format_duration(&total))
-        },
-        TrackingState::Break(ref uid) => {
-            let rest_task = &d.tasks.get(uid).expect("unknown uid");
-
-            let duration =
-                Utc::now().signed_duration_since(d.tracking.timestamp.as_ref().clone());
-
-            let total = get_rest_interval(d, uid);
-
-            format!("Break: '{}' | Elapsed: {} / {}",
-                    rest_task.name, time::format_duration(&duration), time::format_duration(&total))
-        },
-        TrackingState::Paused(ref uid) => {
-            let active_task = &d.tasks.get(uid).expect("unknown uid");
-
-            format!("Paused: '{}' | Elapsed: {} / {}",
-                    active_task.name,
-                    time::format_duration(&d.tracking.elapsed),
-                    time::format_duration(&get_work_interval(d, uid)))
-        },
-
-        _ => format!("")
-    }
-
-}
-
 impl StatusBar {
     fn new() -> StatusBar {
         StatusBar{inner: WidgetPod::new(Label::dynamic(|d: &String, _env| d.clone())),
@@ -239,16 +319,31 @@ impl Widget<AppModel> for StatusBar {
     }
 }
 
+fn get_break_string(d: &AppModel) -> String {
+    let (taken, due) = get_break_stats(&d.tasks, &d.records, &d.records_killed,
+                                       &time::daystart(Local::now()));
+
+    if due <= chrono::Duration::zero() {
+        return format!("Breaks today: {}", time::format_duration(&taken));
+    }
+
+    format!("Breaks today: {} of {} ({}%)", time::format_duration(&taken),
+            time::format_duration(&due),
+            (taken.num_seconds() * 100 / due.num_seconds()).min(100))
+}
+
 fn ui_builder() -> impl Widget<AppModel> {
     let mut root = Flex::column();
 
+    root.add_child(prompt_widget());
+
     let mut main_row = Flex::row().cross_axis_alignment(CrossAxisAlignment::Start);
 
     let mut tasks_column = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
     let mut focus_column = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
 
     focus_column.add_child(Label::new("Focus")
-                           .with_font(FONT_CAPTION_DESCR.clone()));
+                           .with_font(FONT_CAPTION_DESCR));
 
     focus_column.add_spacer(10.0);
 
@@ -259,13 +354,13 @@ fn ui_builder() -> impl Widget<AppModel> {
                     .align_vertical(UnitPoint::LEFT)
                     .padding(10.0)
                     .background(
-                        Painter::new(|ctx: &mut PaintCtx, (shared, filter): &(AppModel, FocusFilter), _env| {
+                        Painter::new(|ctx: &mut PaintCtx, (shared, filter): &(AppModel, FocusFilter), env| {
                             let bounds = ctx.size().to_rect();
                             if shared.focus_filter.eq(filter) {
-                                ctx.fill(bounds, &TASK_COLOR_BG);
+                                ctx.fill(bounds, &env.get(TASK_COLOR_BG));
                             }
                             else {
-                                ctx.stroke(bounds, &TASK_COLOR_BG, 2.0);
+                                ctx.stroke(bounds, &env.get(TASK_COLOR_BG), 2.0);
                             }
                         })
                     )
@@ -292,7 +387,7 @@ fn ui_builder() -> impl Widget<AppModel> {
     focus_column.add_spacer(15.0);
 
     focus_column.add_child(Label::new("Tags")
-                           .with_font(FONT_CAPTION_DESCR.clone()));
+                           .with_font(FONT_CAPTION_DESCR));
 
     focus_column.add_default_spacer();
 
@@ -304,14 +399,14 @@ fn ui_builder() -> impl Widget<AppModel> {
                         .align_vertical(UnitPoint::LEFT)
                         .padding(10.0)
                         .background(
-                            Painter::new(|ctx: &mut PaintCtx, (shared, id): &(AppModel, String), _env| {
+                            Painter::new(|ctx: &mut PaintCtx, (shared, id): &(AppModel, String), env| {
                                 let bounds = ctx.size().to_rect();
                                 if shared.tag_filter.is_some() &&
                                     shared.tag_filter.as_ref().unwrap().eq(id) {
-                                        ctx.fill(bounds, &TASK_COLOR_BG);
+                                        ctx.fill(bounds, &env.get(TASK_COLOR_BG));
                                     }
                                 else {
-                                    ctx.stroke(bounds, &TASK_COLOR_BG, 2.0);
+                                    ctx.stroke(bounds, &env.get(TASK_COLOR_BG), 2.0);
                                 }
                             })
                         )
@@ -429,12 +524,16 @@ fn ui_builder() -> impl Widget<AppModel> {
                 match (d.get_task(&d.selected_task),
                        d.get_task_sum(&d.selected_task).unwrap_or(&TimePrefixSum::new()))
                 {
-                    (Some(task), time) => Some(((task.clone(), TaskViewState{skip_days: 0}, time.clone()), d.show_task_summary)),
+                    (Some(task), time) => {
+                        let completed = d.completed_sessions.get(&task.uid).cloned().unwrap_or(0);
+                        Some(((task.clone(), TaskViewState{skip_days: 0}, time.clone(), completed),
+                              d.show_task_summary))
+                    },
                     _ => None,
                 },
 
-                |d: &mut AppModel, x: Option<((Task, TaskViewState, TimePrefixSum), bool)>| {
-                    if let Some(((_, _ , _), vis)) = x {
+                |d: &mut AppModel, x: Option<((Task, TaskViewState, TimePrefixSum, u32), bool)>| {
+                    if let Some(((_, _ , _, _), vis)) = x {
                         d.show_task_summary = vis;
                     }
                 },
@@ -443,13 +542,13 @@ fn ui_builder() -> impl Widget<AppModel> {
 
     main_row.add_flex_child(tasks_column
                             .padding(10.0)
-                            .border(KeyOrValue::Concrete(APP_BORDER.clone()), 1.0),
+                            .border(APP_BORDER, 1.0),
                             2.0);
 
     let mut time_column = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
 
     time_column.add_child(Label::new("Total time log")
-                          .with_font(FONT_CAPTION_DESCR.clone())
+                          .with_font(FONT_CAPTION_DESCR)
                           .padding(10.0));
 
     time_column.add_child(
@@ -459,13 +558,17 @@ fn ui_builder() -> impl Widget<AppModel> {
                 |_, _ | {},
         )));
 
+    time_column.add_child(Label::new(|d: &AppModel, _env: &_| get_break_string(d))
+                          .with_font(FONT_LOG_DESCR)
+                          .padding(10.0));
+
     time_column.add_default_spacer();
 
-    time_column.add_child(Label::new("Activity log").with_font(FONT_CAPTION_DESCR.clone()).padding(10.0));
+    time_column.add_child(Label::new("Activity log").with_font(FONT_CAPTION_DESCR).padding(10.0));
 
     time_column.add_flex_child(
         Scroll::new(ActivityLogWidget::new())
-            .border(KeyOrValue::Concrete(APP_BORDER.clone()), 1.0), 1.0);
+            .border(APP_BORDER, 1.0), 1.0);
 
     main_row.add_child(time_column);
 
@@ -486,7 +589,7 @@ fn ui_builder() -> impl Widget<AppModel> {
 
     root.with_child(Container::new(StatusBar::new()
                                    .align_horizontal(UnitPoint::CENTER))
-                    .border(KeyOrValue::Concrete(APP_BORDER.clone()), 1.0),
+                    .border(APP_BORDER, 1.0),
     )
         // .debug_paint_layout()
 }
@@ -0,0 +1,55 @@
+#[cfg(unix)]
+use std::path::PathBuf;
+
+#[cfg(unix)]
+use clap::Parser;
+
+#[cfg(unix)]
+use netupi::ipc;
+
+/// Send a request to the running netupi, for example
+/// `netupi_ctl start "some task"`, `netupi_ctl pause` or `netupi_ctl status`.
+#[cfg(unix)]
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+    #[clap(short, long)]
+    config_dir: Option<PathBuf>,
+    #[clap(long)]
+    socket: Option<PathBuf>,
+    /// start <task> | pause | resume | stop | status | list
+    #[clap(required = true)]
+    request: Vec<String>,
+}
+
+#[cfg(unix)]
+pub fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let mut default_config_dir = dirs::config_dir().unwrap_or(PathBuf::new());
+    default_config_dir.push("netupi");
+
+    let socket_path = args.socket.clone().unwrap_or_else(
+        || ipc::default_path(&args.config_dir.clone().unwrap_or(default_config_dir)));
+
+    let reply = ipc::request(&socket_path, &args.request.join(" "))?;
+
+    if let Some(error) = reply.error {
+        return Err(anyhow::anyhow!(error));
+    }
+
+    if let Some(status) = reply.status {
+        println!("{}", if status.text.is_empty() {status.state} else {status.text});
+    }
+
+    for task in reply.tasks.unwrap_or_default() {
+        println!("{}  {:<14} {}", task.uid, task.status, task.name);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn main() -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("netupi_ctl needs Unix domain sockets"))
+}
@@ -0,0 +1,159 @@
+use druid::widget::prelude::*;
+use druid::widget::{Button, Controller, CrossAxisAlignment, Either, Flex, Label,
+                    MainAxisAlignment, SizedBox, TextBox};
+use druid::{commands, FontDescriptor, FontFamily, LocalizedString, Screen, Target, TimerToken,
+            Widget, WidgetExt, WindowDesc, WindowState};
+
+use chrono::prelude::*;
+
+use crate::app_model::*;
+use crate::common::*;
+use crate::theme;
+use crate::time;
+
+/// Word to type before a break can be skipped, with `break_skip_confirm`.
+const SKIP_WORD: &str = "skip";
+
+/// Window over the whole screen for the running break, it closes itself
+/// when the break ends.
+pub fn break_overlay_window() -> WindowDesc<AppModel> {
+    let screen = Screen::get_display_rect();
+
+    WindowDesc::new(theme::themed(break_overlay_widget()))
+        .show_titlebar(false)
+        .set_position(screen.origin())
+        .window_size(screen.size())
+        .set_window_state(WindowState::Maximized)
+        .title(LocalizedString::new("netupi-break-title").with_placeholder("Break"))
+}
+
+fn format_countdown(left: &chrono::Duration) -> String {
+    let secs = left.num_seconds().max(0);
+
+    if secs >= 3600 {
+        return format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    }
+
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// Time until the break can be skipped, zero once it can.
+fn skip_delay_left(model: &AppModel) -> chrono::Duration {
+    let delay = chrono::Duration::seconds(model.config.break_skip_delay_seconds);
+    let passed = Utc::now().signed_duration_since(*model.tracking.timestamp);
+
+    (delay - passed).max(chrono::Duration::zero())
+}
+
+fn can_skip(model: &AppModel) -> bool {
+    matches!(model.tracking.state, TrackingState::Break(_)) &&
+        skip_delay_left(model) <= chrono::Duration::zero() &&
+        (!model.config.break_skip_confirm || model.break_skip_text.trim() == SKIP_WORD)
+}
+
+fn break_overlay_widget() -> impl Widget<AppModel> {
+    let title = Label::new(|model: &AppModel, _env: &Env| match model.tracking.state {
+        TrackingState::Break(ref uid) => format!(
+            "{} after '{}'",
+            if is_long_break_due(model) {"Long break"} else {"Break"},
+            model.tasks.get(uid).map(|t| t.name.clone()).unwrap_or_default()),
+        _ => "".to_string(),
+    }).with_font(FONT_CAPTION_DESCR);
+
+    let countdown = Label::new(|model: &AppModel, _env: &Env| {
+        get_remaining_time(model).map(|left| format_countdown(&left)).unwrap_or_default()
+    }).with_font(FontDescriptor::new(FontFamily::MONOSPACE).with_size(96.0));
+
+    let remaining = Label::new(|model: &AppModel, _env: &Env| match model.tracking.state {
+        TrackingState::Break(ref uid) => {
+            let total = get_rest_interval(model, uid);
+            let end: DateTime<Local> = DateTime::from(*model.tracking.timestamp + total);
+
+            format!("{} break, ends at {}", time::format_duration(&total), end.format("%H:%M"))
+        },
+        _ => "".to_string(),
+    });
+
+    let confirm = TextBox::new()
+        .with_placeholder(format!("type '{}' to skip", SKIP_WORD))
+        .fix_width(200.0)
+        .lens(AppModel::break_skip_text);
+
+    let skip = Button::dynamic(|model: &AppModel, _env: &Env| {
+        let left = skip_delay_left(model);
+
+        if left > chrono::Duration::zero() {
+            format!("Skip ({})", left.num_seconds())
+        } else {
+            "Skip".to_string()
+        }
+    })
+        .on_click(|ctx, model: &mut AppModel, _env| {
+            if !can_skip(model) {
+                return;
+            }
+
+            if let TrackingState::Break(ref uid) = model.tracking.state {
+                // the tracking lives in the main window
+                ctx.submit_command(COMMAND_BREAK_SKIP.with(uid.clone()).to(Target::Global));
+            }
+
+            model.break_skip_text.clear();
+        })
+        .disabled_if(|model: &AppModel, _env| !can_skip(model));
+
+    Flex::column()
+        .main_axis_alignment(MainAxisAlignment::Center)
+        .cross_axis_alignment(CrossAxisAlignment::Center)
+        .with_child(title)
+        .with_spacer(20.0)
+        .with_child(countdown)
+        .with_spacer(10.0)
+        .with_child(remaining)
+        .with_spacer(40.0)
+        .with_child(
+            Flex::row()
+                .with_child(Either::new(|model: &AppModel, _env| model.config.break_skip_confirm,
+                                        confirm, SizedBox::empty()))
+                .with_default_spacer()
+                .with_child(skip))
+        .expand()
+        .controller(OverlayController{timer_id: TimerToken::INVALID})
+}
+
+/// Redraws the countdown every second and closes the window once the break
+/// is over.
+struct OverlayController {
+    timer_id: TimerToken,
+}
+
+impl<W: Widget<AppModel>> Controller<AppModel, W> for OverlayController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppModel,
+             env: &Env) {
+        match event {
+            Event::Timer(id) if *id == self.timer_id => {
+                self.timer_id = ctx.request_timer(UI_TIMER_INTERVAL);
+                ctx.request_update();
+            },
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+
+    fn lifecycle(&mut self, child: &mut W, ctx: &mut LifeCycleCtx, event: &LifeCycle,
+                 data: &AppModel, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.timer_id = ctx.request_timer(UI_TIMER_INTERVAL);
+        }
+
+        child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, child: &mut W, ctx: &mut UpdateCtx, old_data: &AppModel,
+              data: &AppModel, env: &Env) {
+        if !matches!(data.tracking.state, TrackingState::Break(_)) {
+            ctx.submit_command(commands::CLOSE_WINDOW.to(ctx.window_id()));
+        }
+
+        child.update(ctx, old_data, data, env)
+    }
+}
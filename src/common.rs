@@ -1,31 +1,59 @@
-use druid::{Color, FontDescriptor, FontFamily, FontWeight, WidgetId, Selector};
+use druid::{Color, FontDescriptor, Key, WidgetId, Selector};
 use core::time::Duration;
+use std::sync::mpsc::Sender;
 
-pub static TASK_COLOR_BG: Color                 = Color::rgb8(80, 73, 69);
-pub static APP_BORDER: Color                    = Color::rgb8(60, 56, 54);
-pub static TASK_ACTIVE_COLOR_BG: Color          = Color::rgb8(250, 189, 47);
-pub static TASK_REST_COLOR_BG: Color            = Color::rgb8(131, 162, 152);
-pub static TASK_PAUSE_COLOR_BG: Color           = Color::rgb8(211, 134, 155);
-pub static TASK_FOCUS_BORDER: Color             = Color::rgb8(124, 111, 100);
-pub static COLOR_ACTIVE: Color                  = Color::rgb8(255, 255, 255);
-pub static DELETING_TASK_BORDER: Color          = Color::rgb8(204, 36, 29);
-pub static RESTORED_TASK_BORDER: Color          = Color::rgb8(184, 187, 38);
+use crate::task::TimeRecord;
+use crate::ipc::Snapshot;
+
+// colours and fonts of the current theme, set by `theme::apply`
+pub const TASK_COLOR_BG: Key<Color>             = Key::new("netupi.task-color-bg");
+pub const APP_BORDER: Key<Color>                = Key::new("netupi.app-border");
+pub const TASK_ACTIVE_COLOR_BG: Key<Color>      = Key::new("netupi.task-active-color-bg");
+pub const TASK_REST_COLOR_BG: Key<Color>        = Key::new("netupi.task-rest-color-bg");
+pub const TASK_PAUSE_COLOR_BG: Key<Color>       = Key::new("netupi.task-pause-color-bg");
+pub const TASK_OVERTIME_COLOR_BG: Key<Color>    = Key::new("netupi.task-overtime-color-bg");
+pub const TASK_FOCUS_BORDER: Key<Color>         = Key::new("netupi.task-focus-border");
+pub const COLOR_ACTIVE: Key<Color>              = Key::new("netupi.color-active");
+pub const DELETING_TASK_BORDER: Key<Color>      = Key::new("netupi.deleting-task-border");
+pub const RESTORED_TASK_BORDER: Key<Color>      = Key::new("netupi.restored-task-border");
 
 pub static UI_TIMER_INTERVAL: Duration = Duration::from_secs(1);
+pub static CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+/// xprintidle and loginctl are asked this often, off the UI thread
+pub static IDLE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// UI ticks further apart than this mean the computer was suspended or
+/// the clock was changed
+pub static CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(60);
+pub static TRACKING_EXTEND_STEP: Duration = Duration::from_secs(5 * 60);
+pub static TRACKING_BACKDATE_STEP: Duration = Duration::from_secs(10 * 60);
 
-pub static FONT_LOG_DESCR: FontDescriptor = FontDescriptor::new(FontFamily::MONOSPACE).with_size(14.0);
-pub static FONT_CAPTION_DESCR: FontDescriptor = FontDescriptor::new(FontFamily::SYSTEM_UI)
-    .with_weight(FontWeight::BOLD).with_size(18.0);
+pub const FONT_LOG_DESCR: Key<FontDescriptor>     = Key::new("netupi.font-log");
+pub const FONT_CAPTION_DESCR: Key<FontDescriptor> = Key::new("netupi.font-caption");
 
 pub const COMMAND_TASK_NEW:    Selector            = Selector::new("tcmenu.task_new");
 pub const COMMAND_TASK_START:  Selector<String>    = Selector::new("tcmenu.task_start");
 pub const COMMAND_TASK_STOP:   Selector            = Selector::new("tcmenu.task_stop");
 pub const COMMAND_TASK_PAUSE:   Selector           = Selector::new("tcmenu.task_pause");
 pub const COMMAND_TASK_RESUME:   Selector<String>  = Selector::new("tcmenu.task_resume");
+pub const COMMAND_TASK_BREAK:   Selector<String>   = Selector::new("tcmenu.task_break");
+pub const COMMAND_TASK_EXTEND:  Selector           = Selector::new("tcmenu.task_extend");
+pub const COMMAND_TASK_BACKDATE: Selector          = Selector::new("tcmenu.task_backdate");
+pub const COMMAND_TASK_FINISH:  Selector           = Selector::new("tcmenu.task_finish");
 pub const COMMAND_TASK_ARCHIVE: Selector<String>   = Selector::new("tcmenu.task_archive");
 pub const COMMAND_TASK_COMPLETED: Selector<String> = Selector::new("tcmenu.task_completed");
+pub const COMMAND_TIME_ENTRY_NEW: Selector         = Selector::new("tcmenu.time_entry_new");
+pub const COMMAND_SETTINGS_OPEN: Selector          = Selector::new("menu.settings_open");
+pub const COMMAND_SOUND_MUTE: Selector             = Selector::new("menu.sound_mute");
+pub const COMMAND_MINI_WINDOW: Selector            = Selector::new("menu.mini_window");
+pub const COMMAND_BREAK_START:  Selector<String>   = Selector::new("prompt.break_start");
+pub const COMMAND_BREAK_SKIP:   Selector<String>   = Selector::new("prompt.break_skip");
+pub const COMMAND_IDLE_DISCARD: Selector<TimeRecord> = Selector::new("prompt.idle_discard");
+pub const COMMAND_CLOCK_JUMP_WORK: Selector<TimeRecord> = Selector::new("prompt.clock_jump_work");
+pub const COMMAND_IDLE_ASSIGN:  Selector<(TimeRecord, String)> = Selector::new("prompt.idle_assign");
 
 pub const COMMAND_TLIST_REQUEST_FOCUS: Selector    = Selector::new("tlist_request_focus");
+pub const COMMAND_TRACKING_RESTORE: Selector       = Selector::new("tracking_restore");
+pub const COMMAND_IPC_SNAPSHOT: Selector<Sender<Snapshot>> = Selector::new("ipc_snapshot");
 
 pub const COMMAND_EDIT_REQUEST_FOCUS: Selector<WidgetId>  = Selector::new("tedit_request_focus");
 
@@ -33,13 +61,5 @@ pub const TASK_EDIT_WIDGET: WidgetId = WidgetId::reserved(1000);
 pub const TASK_NAME_EDIT_WIDGET: WidgetId = WidgetId::reserved(1001);
 
 // https://freesound.org/people/Jummit/sounds/528561/
-pub type WorkCompleteBytes = &'static [u8; 5124];
-
-pub const WORK_TIMER_VOLUME: f32 = 0.7;
-
-#[cfg(target_os = "macos")]
-pub static SOUND_TASK_FINISH: WorkCompleteBytes = std::include_bytes!("../res/notif.ogg");
-#[cfg(target_os = "linux")]
-pub static SOUND_TASK_FINISH: WorkCompleteBytes = std::include_bytes!("../res/notif.ogg");
-#[cfg(target_os = "windows")]
-pub const SOUND_TASK_FINISH: WorkCompleteBytes = std::include_bytes!("../res/notif.ogg");
+pub static SOUND_NOTIF: &[u8] = std::include_bytes!("../res/notif.ogg");
+pub static SOUND_BELL: &[u8] = std::include_bytes!("../res/bell.ogg");
@@ -0,0 +1,271 @@
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use druid::{Data, Lens};
+use serde::{Serialize, Deserialize};
+
+use anyhow;
+
+/// Work and break durations, a timer needs at least a minute.
+pub const DURATION_MINUTES: RangeInclusive<i64> = 1..=600;
+/// Waits measured in minutes, 0 where it turns the feature off.
+pub const WAIT_MINUTES: RangeInclusive<i64> = 0..=1440;
+pub const CYCLES: RangeInclusive<u32> = 0..=100;
+pub const DELAY_SECONDS: RangeInclusive<i64> = 0..=3600;
+pub const TIMEOUT_SECONDS: RangeInclusive<u32> = 0..=3600;
+pub const FONT_SIZE: RangeInclusive<f64> = 8.0..=32.0;
+pub const VOLUME: RangeInclusive<f64> = 0.0..=1.0;
+
+/// `value` if it is in `range`, the nearest end of it otherwise.
+pub fn clamp<T: PartialOrd + Copy>(value: T, range: &RangeInclusive<T>) -> T {
+    if value < *range.start() {
+        *range.start()
+    } else if value > *range.end() {
+        *range.end()
+    } else {
+        value
+    }
+}
+
+/// User settings, stored as json next to the database unless `--config`
+/// points elsewhere. Missing fields take their default values, so older
+/// files keep working.
+#[derive(Debug, Clone, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// work and break durations of new tasks
+    pub default_work_minutes: i64,
+    pub default_break_minutes: i64,
+    /// false mutes all sounds
+    pub sound: bool,
+    /// sounds as understood by `Sound::from_setting`, a task can have its
+    /// own sound for a finished pomodoro
+    pub sound_work_finished: String,
+    pub sound_break_finished: String,
+    pub sound_session_start: String,
+    /// looped while work is tracked: "tick", "white", "brown", "none" or a
+    /// file
+    pub ambient_sound: String,
+    pub ambient_volume: f64,
+    /// 0.0 to 1.0
+    pub volume: f64,
+    pub notifications: bool,
+    /// seconds, 0 for the desktop's default
+    pub notification_timeout: u32,
+    /// "low", "normal" or "critical"
+    pub notification_urgency: String,
+    /// "dark" or "light"
+    pub theme: String,
+    pub font_size: f64,
+    /// a long break follows every `long_break_cycle` completed pomodoros,
+    /// 0 turns long breaks off
+    pub long_break_cycle: u32,
+    pub long_break_minutes: i64,
+    /// the pomodoro counter starts over after this long without work
+    pub cycle_reset_minutes: i64,
+    /// start the next pomodoro of the same task when a break ends
+    pub auto_start_work: bool,
+    /// go on without breaks when a pomodoro is completed
+    pub skip_breaks: bool,
+    /// ask before starting a break
+    pub confirm_break: bool,
+    /// pomodoros run in a row by `auto_start_work`, 0 for no limit
+    pub auto_cycles: u32,
+    /// keep counting past the work interval instead of ending the pomodoro
+    pub flow_mode: bool,
+    /// count up without the pomodoro timer for all tasks
+    pub stopwatch: bool,
+    /// where idle time comes from: "auto", "x11", "logind", "fake" or "none"
+    pub idle_source: String,
+    /// ask what to do with time away from the computer longer than this,
+    /// 0 turns idle detection off
+    pub idle_minutes: i64,
+    /// cover the screen during breaks
+    pub break_overlay: bool,
+    /// the break overlay lets the break be skipped only after this long
+    pub break_skip_delay_seconds: i64,
+    /// skipping from the break overlay needs the word "skip" typed
+    pub break_skip_confirm: bool,
+    /// remind to track after this long without a session, repeated as
+    /// long as nothing is tracked, 0 turns reminders off
+    pub reminder_minutes: i64,
+    /// reminders only come between these times, "HH:MM", local time; an end
+    /// before the start spans midnight
+    pub work_hours_start: String,
+    pub work_hours_end: String,
+    /// reminders on Saturday and Sunday too
+    pub reminder_weekends: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            default_work_minutes: 50,
+            default_break_minutes: 10,
+            sound: true,
+            sound_work_finished: "notif".to_string(),
+            sound_break_finished: "bell".to_string(),
+            sound_session_start: "none".to_string(),
+            ambient_sound: "none".to_string(),
+            ambient_volume: 0.3,
+            volume: 0.7,
+            notifications: true,
+            notification_timeout: 0,
+            notification_urgency: "normal".to_string(),
+            theme: "dark".to_string(),
+            font_size: 14.0,
+            long_break_cycle: 4,
+            long_break_minutes: 30,
+            cycle_reset_minutes: 120,
+            auto_start_work: false,
+            skip_breaks: false,
+            confirm_break: false,
+            auto_cycles: 0,
+            flow_mode: false,
+            stopwatch: false,
+            idle_source: "auto".to_string(),
+            idle_minutes: 5,
+            break_overlay: false,
+            break_skip_delay_seconds: 0,
+            break_skip_confirm: false,
+            reminder_minutes: 0,
+            work_hours_start: "09:00".to_string(),
+            work_hours_end: "18:00".to_string(),
+            reminder_weekends: false,
+        }
+    }
+}
+
+impl Config {
+    /// Numbers moved into the ranges the settings window allows, a config
+    /// file edited by hand may hold anything.
+    pub fn clamped(mut self) -> Config {
+        self.default_work_minutes = clamp(self.default_work_minutes, &DURATION_MINUTES);
+        self.default_break_minutes = clamp(self.default_break_minutes, &DURATION_MINUTES);
+        self.long_break_minutes = clamp(self.long_break_minutes, &DURATION_MINUTES);
+        self.cycle_reset_minutes = clamp(self.cycle_reset_minutes, &WAIT_MINUTES);
+        self.idle_minutes = clamp(self.idle_minutes, &WAIT_MINUTES);
+        self.reminder_minutes = clamp(self.reminder_minutes, &WAIT_MINUTES);
+        self.long_break_cycle = clamp(self.long_break_cycle, &CYCLES);
+        self.auto_cycles = clamp(self.auto_cycles, &CYCLES);
+        self.break_skip_delay_seconds = clamp(self.break_skip_delay_seconds, &DELAY_SECONDS);
+        self.notification_timeout = clamp(self.notification_timeout, &TIMEOUT_SECONDS);
+        self.font_size = clamp(self.font_size, &FONT_SIZE);
+        self.volume = clamp(self.volume, &VOLUME);
+        self.ambient_volume = clamp(self.ambient_volume, &VOLUME);
+        self
+    }
+
+    /// Whether `at` falls into the working hours, always if they can't be
+    /// parsed.
+    pub fn is_working_time(&self, at: DateTime<Local>) -> bool {
+        if !self.reminder_weekends && matches!(at.weekday(), Weekday::Sat | Weekday::Sun) {
+            return false;
+        }
+
+        let parse = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M");
+
+        let (start, end) = match (parse(&self.work_hours_start), parse(&self.work_hours_end)) {
+            (Ok(start), Ok(end)) => (start, end),
+            _ => {
+                println!("config error: working hours '{}' - '{}'",
+                         self.work_hours_start, self.work_hours_end);
+                return true;
+            }
+        };
+
+        let time = at.time();
+
+        if start <= end {
+            start <= time && time < end
+        } else {
+            start <= time || time < end
+        }
+    }
+}
+
+/// Config file in the config directory `dir`.
+pub fn default_path(dir: &PathBuf) -> PathBuf {
+    let mut path = dir.clone();
+    path.push("config.json");
+    path
+}
+
+/// Read the config file `path`, defaults if there is none yet.
+pub fn load(path: &PathBuf) -> anyhow::Result<Config> {
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    let config = serde_json::from_str::<Config>(&fs::read_to_string(path)?)?.clamped();
+
+    println!("config load ok | {:?}", path);
+
+    Ok(config)
+}
+
+pub fn save(path: &PathBuf, config: &Config) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, serde_json::to_string_pretty(config)?)?;
+
+    println!("config save ok | {:?}", path);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(start: &str, end: &str, weekends: bool) -> Config {
+        Config{work_hours_start: start.to_string(), work_hours_end: end.to_string(),
+               reminder_weekends: weekends, ..Config::default()}
+    }
+
+    // 2022-01-31 is a Monday
+    fn at(day: u32, h: u32, m: u32) -> DateTime<Local> {
+        Local.ymd(2022, 1, day).and_hms(h, m, 0)
+    }
+
+    #[test]
+    fn day_span() {
+        let config = hours("09:00", "18:00", false);
+
+        assert!(config.is_working_time(at(31, 9, 0)));
+        assert!(config.is_working_time(at(31, 17, 59)));
+        assert!(!config.is_working_time(at(31, 18, 0)));
+        assert!(!config.is_working_time(at(31, 8, 59)));
+    }
+
+    #[test]
+    fn span_across_midnight() {
+        let config = hours("22:00", "06:00", true);
+
+        assert!(config.is_working_time(at(31, 23, 0)));
+        assert!(config.is_working_time(at(31, 0, 30)));
+        assert!(config.is_working_time(at(31, 5, 59)));
+        assert!(!config.is_working_time(at(31, 6, 0)));
+        assert!(!config.is_working_time(at(31, 12, 0)));
+        assert!(!config.is_working_time(at(31, 21, 59)));
+    }
+
+    #[test]
+    fn weekends() {
+        // Saturday and Sunday
+        assert!(!hours("09:00", "18:00", false).is_working_time(at(29, 12, 0)));
+        assert!(!hours("09:00", "18:00", false).is_working_time(at(30, 12, 0)));
+        assert!(hours("09:00", "18:00", true).is_working_time(at(29, 12, 0)));
+        assert!(hours("09:00", "18:00", true).is_working_time(at(30, 12, 0)));
+        assert!(hours("09:00", "18:00", false).is_working_time(at(28, 12, 0)));
+    }
+
+    #[test]
+    fn bad_hours_always_work() {
+        assert!(hours("nine", "18:00", false).is_working_time(at(31, 3, 0)));
+    }
+}
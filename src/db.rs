@@ -17,7 +17,10 @@ use anyhow;
 
 use chrono::{DateTime, Utc, TimeZone};
 
+use druid::TimerToken;
+
 use crate::task::*;
+use crate::app_model::{TrackingCtx, TrackingState};
 
 struct DurationWrapper(chrono::Duration);
 
@@ -33,6 +36,18 @@ impl FromSql for DurationWrapper {
     }
 }
 
+impl ToSql for RecordKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for RecordKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Ok(RecordKind::from_str(value.as_str()?).unwrap_or(RecordKind::Work))
+    }
+}
+
 struct TimeWrapper(DateTime<Utc>);
 
 impl ToSql for TimeWrapper {
@@ -49,6 +64,124 @@ impl FromSql for TimeWrapper {
     }
 }
 
+/// Schema migrations, applied in order. Migration `i` upgrades a database
+/// from `user_version` i to i + 1, so entries must never be edited or
+/// reordered once released -- append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE IF NOT EXISTS tasks (
+         uid text primary key,
+         seq integer not null,
+         name text not null,
+         description text not null,
+         tags text not null,
+         priority integer not null,
+         status text not null,
+         work_duration integer not null,
+         break_duration integer not null,
+         color integer not null
+     );
+     CREATE TABLE IF NOT EXISTS time_records (
+         ts_from INTEGER PRIMARY KEY,
+         ts_to INTEGER,
+         uid TEXT NOT NULL
+     );",
+    // 2: open records of running sessions are checkpointed periodically
+    "ALTER TABLE time_records ADD COLUMN ts_checkpoint INTEGER;",
+    // 3: tracking state survives restarts
+    "CREATE TABLE IF NOT EXISTS tracking (
+         id INTEGER PRIMARY KEY CHECK (id = 0),
+         state TEXT NOT NULL,
+         ts INTEGER NOT NULL,
+         elapsed INTEGER NOT NULL
+     );",
+    // 4: records get an id of their own, several may start at the same time
+    "CREATE TABLE time_records_new (
+         id INTEGER PRIMARY KEY AUTOINCREMENT,
+         ts_from INTEGER NOT NULL,
+         ts_to INTEGER,
+         uid TEXT NOT NULL,
+         ts_checkpoint INTEGER
+     );
+     INSERT INTO time_records_new (ts_from, ts_to, uid, ts_checkpoint)
+         SELECT ts_from, ts_to, uid, ts_checkpoint FROM time_records ORDER BY ts_from;
+     DROP TABLE time_records;
+     ALTER TABLE time_records_new RENAME TO time_records;
+     CREATE INDEX time_records_ts_from ON time_records (ts_from);
+     ALTER TABLE tracking ADD COLUMN record_id INTEGER;",
+    // 5: breaks and pauses are recorded next to work
+    "ALTER TABLE time_records ADD COLUMN kind TEXT NOT NULL DEFAULT 'Work';",
+    // 6: records of one pomodoro are grouped into a session
+    "CREATE TABLE IF NOT EXISTS sessions (
+         id INTEGER PRIMARY KEY AUTOINCREMENT,
+         uid TEXT NOT NULL,
+         ts_from INTEGER NOT NULL,
+         ts_to INTEGER,
+         outcome TEXT NOT NULL
+     );
+     ALTER TABLE time_records ADD COLUMN session_id INTEGER REFERENCES sessions(id);
+     ALTER TABLE tracking ADD COLUMN session_id INTEGER;",
+    // 7: position in the long break cycle
+    "ALTER TABLE tracking ADD COLUMN cycle INTEGER NOT NULL DEFAULT 0;",
+    // 8: pomodoros run in a row without user action
+    "ALTER TABLE tracking ADD COLUMN auto_run INTEGER NOT NULL DEFAULT 0;",
+    // 9: tasks tracked without the pomodoro timer
+    "ALTER TABLE tasks ADD COLUMN stopwatch INTEGER NOT NULL DEFAULT 0;",
+    // 10: running pomodoro extended past the task's work duration
+    "ALTER TABLE tracking ADD COLUMN extended INTEGER NOT NULL DEFAULT 0;",
+    // 11: sound played when the task's pomodoro finishes, empty for the default
+    "ALTER TABLE tasks ADD COLUMN sound TEXT NOT NULL DEFAULT '';",
+];
+
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+pub fn get_schema_version(conn: &Connection) -> anyhow::Result<i64> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+fn backup(file_path: &PathBuf, version: i64) -> anyhow::Result<()> {
+    let mut backup_path = file_path.clone();
+    backup_path.set_extension(format!("db.v{}.bak", version));
+
+    fs::copy(file_path, &backup_path)?;
+
+    println!("db backup ok | {:?}", &backup_path);
+
+    Ok(())
+}
+
+fn migrate(conn: &mut Connection, file_path: &PathBuf) -> anyhow::Result<()> {
+    let version = get_schema_version(conn)?;
+
+    if version > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "database {:?} has schema version {}, this netupi supports up to {}",
+            file_path, version, SCHEMA_VERSION));
+    }
+
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let has_tables: i64 = conn.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table'", [], |row| row.get(0))?;
+
+    if has_tables > 0 {
+        backup(file_path, version)?;
+    }
+
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.execute_batch(&format!("PRAGMA user_version = {}", i + 1))?;
+        tx.commit()?;
+
+        println!("db migration ok | v{} -> v{}", i, i + 1);
+    }
+
+    Ok(())
+}
+
 pub fn init(mut path_buf: PathBuf) -> anyhow::Result<Connection>
 {
     let dir = path_buf.to_str().unwrap();
@@ -58,38 +191,16 @@ pub fn init(mut path_buf: PathBuf) -> anyhow::Result<Connection>
 
     let file_path = path_buf.to_str().unwrap();
 
-    let conn = Connection::open(file_path)?;
+    let mut conn = Connection::open(file_path)?;
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS tasks (
-             uid text primary key,
-             seq integer not null,
-             name text not null,
-             description text not null,
-             tags text not null,
-             priority integer not null,
-             status text not null,
-             work_duration integer not null,
-             break_duration integer not null,
-             color integer not null
-         )",
-        [],
-    )?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS time_records (
-             ts_from INTEGER PRIMARY KEY,
-             ts_to INTEGER,
-             uid TEXT NOT NULL
-         )",
-        [],
-    )?;
+    migrate(&mut conn, &path_buf)?;
 
     Ok(conn)
 }
 
 pub fn add_task(conn: Rc<Connection>, task: &Task) -> anyhow::Result<()> {
     conn.execute(
-        "INSERT INTO tasks (uid, name, description, tags, priority, status, work_duration, break_duration, color, seq) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        "INSERT INTO tasks (uid, name, description, tags, priority, status, work_duration, break_duration, color, seq, stopwatch, sound) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
         params![&task.uid, &task.name, &task.description,
                 &serde_json::to_string(&Wrapper::new(&task.tags)).unwrap(),
                 &task.priority.to_string(), &serde_json::to_string(&task.task_status).unwrap(),
@@ -97,6 +208,8 @@ pub fn add_task(conn: Rc<Connection>, task: &Task) -> anyhow::Result<()> {
                 &DurationWrapper(*task.break_duration),
                 task.color.as_rgba_u32(),
                 &task.seq.to_string(),
+                task.stopwatch,
+                &task.sound,
         ],
     )?;
 
@@ -107,12 +220,13 @@ pub fn add_task(conn: Rc<Connection>, task: &Task) -> anyhow::Result<()> {
 
 pub fn update_task(conn: Rc<Connection>, task: &Task) -> anyhow::Result<()> {
     conn.execute(
-        "UPDATE tasks SET name = ?1, description = ?2, tags = ?3, priority = ?4, status = ?5, work_duration = ?6, break_duration = ?7, seq = ?8, color = ?9 WHERE uid = ?10;",
+        "UPDATE tasks SET name = ?1, description = ?2, tags = ?3, priority = ?4, status = ?5, work_duration = ?6, break_duration = ?7, seq = ?8, color = ?9, stopwatch = ?10, sound = ?11 WHERE uid = ?12;",
         params![&task.name, &task.description,
                 &serde_json::to_string(&Wrapper::new(&task.tags)).unwrap(),
                 &task.priority.to_string(), &serde_json::to_string(&task.task_status).unwrap(),
                 &DurationWrapper(*task.work_duration), &DurationWrapper(*task.break_duration),
-                &task.seq.to_string(), task.color.as_rgba_u32(), &task.uid],
+                &task.seq.to_string(), task.color.as_rgba_u32(), task.stopwatch, &task.sound,
+                &task.uid],
     )?;
 
     println!("update ok | t: {:?}", &task);
@@ -163,6 +277,8 @@ pub fn get_tasks(conn: Rc<Connection>) -> anyhow::Result<(TaskMap, TagSet)>
             break_duration : Rc::new(rest_duration.0),
             color          : druid::Color::from_rgba32_u32(row.get::<usize, u32>(9)?)
                              .with_alpha(1.0),
+            stopwatch      : row.get(10)?,
+            sound          : row.get(11)?,
         })
     })?;
 
@@ -182,23 +298,131 @@ pub fn get_tasks(conn: Rc<Connection>) -> anyhow::Result<(TaskMap, TagSet)>
     Ok((tasks, tags))
 }
 
-pub fn add_time_record(conn: Rc<Connection>, record: &TimeRecord) -> anyhow::Result<()>
+/// Insert a new record, `record.id` is ignored. Returns the id of the
+/// inserted record.
+pub fn add_time_record(conn: Rc<Connection>, record: &TimeRecord) -> anyhow::Result<RecordId>
 {
     conn.execute(
-        "INSERT INTO time_records (ts_from, ts_to, uid) VALUES (?1, ?2, ?3)",
-        params![TimeWrapper(*record.from), TimeWrapper(*record.to), record.uid],
+        "INSERT INTO time_records (ts_from, ts_to, uid, kind, session_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![TimeWrapper(*record.from), TimeWrapper(*record.to), record.uid, record.kind,
+                record.session_id],
     )?;
 
     println!("time record insert ok | t: {:?}", &record);
 
+    Ok(conn.last_insert_rowid())
+}
+
+/// Insert a record removed earlier back under its own id.
+pub fn restore_time_record(conn: Rc<Connection>, record: &TimeRecord) -> anyhow::Result<()>
+{
+    conn.execute(
+        "INSERT INTO time_records (id, ts_from, ts_to, uid, kind, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![record.id, TimeWrapper(*record.from), TimeWrapper(*record.to), record.uid,
+                record.kind, record.session_id],
+    )?;
+
+    println!("time record restore ok | t: {:?}", &record);
+
+    Ok(())
+}
+
+pub fn update_time_record(conn: Rc<Connection>, record: &TimeRecord) -> anyhow::Result<()>
+{
+    conn.execute(
+        "UPDATE time_records SET ts_from = ?1, ts_to = ?2, uid = ?3 WHERE id = ?4",
+        params![TimeWrapper(*record.from), TimeWrapper(*record.to), record.uid, record.id],
+    )?;
+
+    println!("time record update ok | t: {:?}", &record);
+
+    Ok(())
+}
+
+pub fn reassign_time_records(conn: Rc<Connection>, keys: &[RecordId], uid: &String)
+                             -> anyhow::Result<()>
+{
+    let tx = conn.unchecked_transaction()?;
+
+    for id in keys {
+        tx.execute(
+            "UPDATE time_records SET uid = ?1 WHERE id = ?2",
+            params![uid, id],
+        )?;
+    }
+
+    tx.commit()?;
+
+    println!("time records reassign ok | {} record(s) to {}", keys.len(), uid);
+
+    Ok(())
+}
+
+/// Shorten `first` to end where `second` starts and insert `second`.
+/// Returns the id of `second`.
+pub fn split_time_record(conn: Rc<Connection>, first: &TimeRecord, second: &TimeRecord)
+                         -> anyhow::Result<RecordId>
+{
+    let tx = conn.unchecked_transaction()?;
+
+    tx.execute(
+        "UPDATE time_records SET ts_to = ?1 WHERE id = ?2",
+        params![TimeWrapper(*first.to), first.id],
+    )?;
+    tx.execute(
+        "INSERT INTO time_records (ts_from, ts_to, uid, kind, session_id)
+         VALUES (?1, ?2, ?3, ?4, (SELECT session_id FROM time_records WHERE id = ?5))",
+        params![TimeWrapper(*second.from), TimeWrapper(*second.to), second.uid, second.kind,
+                first.id],
+    )?;
+
+    let id = tx.last_insert_rowid();
+
+    tx.commit()?;
+
+    println!("time record split ok | t: {:?} {:?}", &first, &second);
+
+    Ok(id)
+}
+
+/// Extend the record `merged.id` to `merged.to` and delete the records
+/// `removed`.
+pub fn merge_time_records(conn: Rc<Connection>, merged: &TimeRecord, removed: &[RecordId],
+                          moved: &[TimeRecord]) -> anyhow::Result<()>
+{
+    let tx = conn.unchecked_transaction()?;
+
+    for record in moved {
+        tx.execute(
+            "UPDATE time_records SET ts_from = ?1, ts_to = ?2 WHERE id = ?3",
+            params![TimeWrapper(*record.from), TimeWrapper(*record.to), record.id],
+        )?;
+    }
+
+    for id in removed {
+        tx.execute(
+            "DELETE FROM time_records WHERE id = ?1",
+            params![id],
+        )?;
+    }
+
+    tx.execute(
+        "UPDATE time_records SET ts_to = ?1 WHERE id = ?2",
+        params![TimeWrapper(*merged.to), merged.id],
+    )?;
+
+    tx.commit()?;
+
+    println!("time record merge ok | t: {:?}", &merged);
+
     Ok(())
 }
 
 pub fn remove_time_record(conn: Rc<Connection>, record: &TimeRecord) -> anyhow::Result<()>
 {
     conn.execute(
-        "DELETE FROM time_records WHERE ts_from = ?1",
-        params![TimeWrapper(*record.from)],
+        "DELETE FROM time_records WHERE id = ?1",
+        params![record.id],
     )?;
 
     println!("remove ok | t: {:?}", &record);
@@ -206,22 +430,287 @@ pub fn remove_time_record(conn: Rc<Connection>, record: &TimeRecord) -> anyhow::
     Ok(())
 }
 
+/// Insert a record for a session that is still running, `ts_to` stays NULL
+/// until the session is closed with `close_time_record`. Returns the id of
+/// the new record.
+pub fn open_time_record(conn: Rc<Connection>, from: &DateTime<Utc>, uid: &String,
+                        kind: RecordKind, session_id: Option<SessionId>)
+                        -> anyhow::Result<RecordId>
+{
+    conn.execute(
+        "INSERT INTO time_records (ts_from, ts_to, uid, ts_checkpoint, kind, session_id) VALUES (?1, NULL, ?2, ?1, ?3, ?4)",
+        params![TimeWrapper(*from), uid, kind, session_id],
+    )?;
+
+    println!("time record open ok | t: {:?} {} {:?}", from, uid, kind);
+
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn checkpoint_time_record(conn: Rc<Connection>, id: RecordId, now: &DateTime<Utc>)
+                              -> anyhow::Result<()>
+{
+    conn.execute(
+        "UPDATE time_records SET ts_checkpoint = ?1 WHERE id = ?2 AND ts_to IS NULL",
+        params![TimeWrapper(*now), id],
+    )?;
+
+    Ok(())
+}
+
+pub fn close_time_record(conn: Rc<Connection>, record: &TimeRecord) -> anyhow::Result<()>
+{
+    conn.execute(
+        "UPDATE time_records SET ts_to = ?1, ts_checkpoint = NULL WHERE id = ?2",
+        params![TimeWrapper(*record.to), record.id],
+    )?;
+
+    println!("time record close ok | t: {:?}", &record);
+
+    Ok(())
+}
+
+/// Records left open by a session that never stopped (crash, kill, power
+/// loss). `to` of the returned records is the last checkpoint.
+pub fn get_open_time_records(conn: Rc<Connection>) -> anyhow::Result<Vec<TimeRecord>>
+{
+    let mut stmt = conn.prepare(
+        "SELECT id, ts_from, ts_checkpoint, uid, kind, session_id FROM time_records WHERE ts_to IS NULL")?;
+
+    let rows = stmt.query_map([], |row| {
+        let ts_from: TimeWrapper = row.get(1)?;
+        let ts_checkpoint: Option<TimeWrapper> = row.get(2)?;
+
+        Ok(TimeRecord {
+            id: row.get(0)?,
+            from: Rc::new(ts_from.0),
+            to: Rc::new(ts_checkpoint.map(|t| t.0).unwrap_or(ts_from.0)),
+            uid: row.get(3)?,
+            kind: row.get(4)?,
+            session_id: row.get(5)?,
+        })
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<Vec<TimeRecord>>>()?)
+}
+
 pub fn get_time_records(conn: Rc<Connection>, from: &DateTime<Utc>, to: &DateTime<Utc>)
                         -> anyhow::Result<TimeRecordMap>
 {
-    let mut stmt = conn.prepare("SELECT * FROM time_records WHERE ts_from >= ?1 AND ts_to < ?2")?;
+    let mut stmt = conn.prepare("SELECT id, ts_from, ts_to, uid, kind, session_id FROM time_records WHERE ts_from >= ?1 AND ts_to < ?2")?;
 
     let rows = stmt.query_map(params![TimeWrapper(*from), TimeWrapper(*to)],
         |row| {
-            let ts_from: TimeWrapper = row.get(0)?;
-            let ts_to: TimeWrapper = row.get(1)?;
-
-            Ok(TimeRecord {
+            let id: RecordId = row.get(0)?;
+            let ts_from: TimeWrapper = row.get(1)?;
+            let ts_to: Option<TimeWrapper> = row.get(2)?;
+            let uid: String = row.get(3)?;
+            let kind: RecordKind = row.get(4)?;
+            let session_id: Option<SessionId> = row.get(5)?;
+
+            Ok(ts_to.map(|ts_to| TimeRecord {
+                id,
                 from: Rc::new(ts_from.0),
                 to: Rc::new(ts_to.0),
-                uid: row.get(2)?
-            })
+                uid,
+                kind,
+                session_id
+            }))
         })?;
 
-    Ok(TimeRecordMap::from_iter(rows.map(|x| (*(x.as_ref().unwrap().from).clone(), x.unwrap()))))
+    // open records (NULL ts_to) belong to running or crashed sessions
+    Ok(TimeRecordMap::from_iter(rows.filter_map(|x| x.unwrap())
+                                .map(|x| (x.id, x))))
+}
+
+pub fn open_session(conn: Rc<Connection>, from: &DateTime<Utc>, uid: &String)
+                    -> anyhow::Result<SessionId>
+{
+    conn.execute(
+        "INSERT INTO sessions (uid, ts_from, ts_to, outcome) VALUES (?1, ?2, NULL, ?3)",
+        params![uid, TimeWrapper(*from), SessionOutcome::Running.to_string()],
+    )?;
+
+    println!("session open ok | t: {:?} {}", from, uid);
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Move the start of the running session's open record back to `from`.
+pub fn move_open_time_record(conn: Rc<Connection>, id: RecordId, from: &DateTime<Utc>)
+                             -> anyhow::Result<()>
+{
+    conn.execute(
+        "UPDATE time_records SET ts_from = ?1 WHERE id = ?2 AND ts_to IS NULL",
+        params![TimeWrapper(*from), id],
+    )?;
+
+    println!("time record move ok | t: {} {:?}", id, from);
+
+    Ok(())
+}
+
+pub fn move_session(conn: Rc<Connection>, id: SessionId, from: &DateTime<Utc>)
+                    -> anyhow::Result<()>
+{
+    conn.execute(
+        "UPDATE sessions SET ts_from = ?1 WHERE id = ?2",
+        params![TimeWrapper(*from), id],
+    )?;
+
+    Ok(())
+}
+
+pub fn close_session(conn: Rc<Connection>, id: SessionId, to: &DateTime<Utc>,
+                     outcome: SessionOutcome) -> anyhow::Result<()>
+{
+    conn.execute(
+        "UPDATE sessions SET ts_to = ?1, outcome = ?2 WHERE id = ?3",
+        params![TimeWrapper(*to), outcome.to_string(), id],
+    )?;
+
+    println!("session close ok | t: {} {:?}", id, outcome);
+
+    Ok(())
+}
+
+/// Number of sessions completed by the timer, per task.
+pub fn get_completed_sessions(conn: Rc<Connection>) -> anyhow::Result<SessionCounts>
+{
+    let mut stmt = conn.prepare(
+        "SELECT uid, count(*) FROM sessions WHERE outcome = ?1 GROUP BY uid")?;
+
+    let rows = stmt.query_map(params![SessionOutcome::Completed.to_string()], |row| {
+        Ok((row.get::<usize, String>(0)?, row.get::<usize, u32>(1)?))
+    })?;
+
+    Ok(SessionCounts::from_iter(rows.collect::<rusqlite::Result<Vec<(String, u32)>>>()?))
+}
+
+pub fn save_tracking(conn: Rc<Connection>, tracking: &TrackingCtx) -> anyhow::Result<()>
+{
+    conn.execute(
+        "INSERT OR REPLACE INTO tracking (id, state, ts, elapsed, record_id, session_id, cycle, auto_run, extended) VALUES (0, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![&serde_json::to_string(&tracking.state).unwrap(),
+                TimeWrapper(*tracking.timestamp),
+                DurationWrapper(*tracking.elapsed),
+                tracking.record_id,
+                tracking.session_id,
+                tracking.cycle,
+                tracking.auto_run,
+                DurationWrapper(*tracking.extended)],
+    )?;
+
+    Ok(())
+}
+
+/// Tracking state saved by the last run, the timer is not armed.
+pub fn get_tracking(conn: Rc<Connection>) -> anyhow::Result<Option<TrackingCtx>>
+{
+    let mut stmt = conn.prepare("SELECT state, ts, elapsed, record_id, session_id, cycle, auto_run, extended FROM tracking WHERE id = 0")?;
+
+    let mut rows = stmt.query_map([], |row| {
+        let state_str: String = row.get(0)?;
+        let ts: TimeWrapper = row.get(1)?;
+        let elapsed: DurationWrapper = row.get(2)?;
+
+        Ok(TrackingCtx {
+            state: serde_json::from_str::<TrackingState>(&state_str)
+                   .unwrap_or(TrackingState::Inactive),
+            timestamp: Rc::new(ts.0),
+            timer_id: Rc::new(TimerToken::INVALID),
+            elapsed: Rc::new(elapsed.0),
+            record_id: row.get(3)?,
+            session_id: row.get(4)?,
+            cycle: row.get(5)?,
+            auto_run: row.get(6)?,
+            extended: Rc::new(row.get::<usize, DurationWrapper>(7)?.0),
+        })
+    })?;
+
+    Ok(rows.next().transpose()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::TempDir;
+
+    fn all_time() -> (DateTime<Utc>, DateTime<Utc>) {
+        (Utc.timestamp_millis(0), Utc.ymd(2100, 1, 1).and_hms(0, 0, 0))
+    }
+
+    #[test]
+    fn fresh_init_reaches_schema_version() {
+        let dir = TempDir::new();
+        let conn = Rc::new(init(dir.path()).unwrap());
+
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+
+        let from = Utc.timestamp_millis(1_000_000);
+        let to = Utc.timestamp_millis(2_000_000);
+        let id = add_time_record(conn.clone(), &TimeRecord{id: 0, from: Rc::new(from),
+                                                           to: Rc::new(to), uid: "a".to_string(),
+                                                           kind: RecordKind::Break,
+                                                           session_id: None}).unwrap();
+
+        let (from, to) = all_time();
+        let records = get_time_records(conn, &from, &to).unwrap();
+
+        assert_eq!(records.get(&id).map(|r| r.kind), Some(RecordKind::Break));
+        assert!(!dir.join("netupi.db.v0.bak").exists());
+    }
+
+    #[test]
+    fn upgrade_from_v0_keeps_rows() {
+        let dir = TempDir::new();
+
+        {
+            // before migrations the tables were created as they are in the first one
+            let conn = Connection::open(dir.join("netupi.db")).unwrap();
+            conn.execute_batch(MIGRATIONS[0]).unwrap();
+            conn.execute_batch(
+                "INSERT INTO tasks VALUES ('a', 1, 'Old task', '', '[]', 0, '\"NeedsAction\"',
+                                           3000000, 600000, 255);
+                 INSERT INTO time_records VALUES (1000000, 2000000, 'a');").unwrap();
+            assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        }
+
+        let conn = Rc::new(init(dir.path()).unwrap());
+
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(dir.join("netupi.db.v0.bak").exists());
+
+        let (tasks, _) = get_tasks(conn.clone()).unwrap();
+        let task = tasks.get("a").unwrap();
+
+        assert_eq!(task.name, "Old task");
+        assert_eq!(*task.work_duration, Duration::minutes(50));
+        assert!(!task.stopwatch);
+        assert!(task.sound.is_empty());
+
+        let (from, to) = all_time();
+        let records: Vec<TimeRecord> = get_time_records(conn, &from, &to).unwrap()
+            .into_iter().map(|(_, r)| r).collect();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(*records[0].from, Utc.timestamp_millis(1_000_000));
+        assert_eq!(*records[0].to, Utc.timestamp_millis(2_000_000));
+        assert_eq!(records[0].uid, "a");
+        assert_eq!(records[0].kind, RecordKind::Work);
+    }
+
+    #[test]
+    fn newer_version_is_refused() {
+        let dir = TempDir::new();
+
+        init(dir.path()).unwrap()
+            .execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1)).unwrap();
+
+        assert!(init(dir.path()).is_err());
+
+        let conn = Connection::open(dir.join("netupi.db")).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION + 1);
+    }
 }
@@ -0,0 +1,361 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow;
+use chrono::prelude::*;
+
+use crate::app_model::{TrackingCtx, TrackingState};
+use crate::task::{RecordKind, TimeRecord};
+
+/// Time since the last user input, polled while a session runs.
+pub trait IdleSource {
+    fn idle_time(&self) -> anyhow::Result<Duration>;
+}
+
+type Reading = Option<Result<(Duration, Instant), String>>;
+
+/// Polls another source in its own thread, so that a slow `xprintidle` or
+/// `loginctl` never holds up the UI. Answers with the latest reading, aged
+/// by the time since it was taken.
+pub struct BackgroundIdle {
+    latest: Arc<Mutex<Reading>>,
+}
+
+impl BackgroundIdle {
+    pub fn new(inner: Box<dyn IdleSource + Send>, interval: Duration) -> BackgroundIdle {
+        let latest = Arc::new(Mutex::new(None));
+        let weak = Arc::downgrade(&latest);
+
+        thread::spawn(move || {
+            // ends once the source is dropped
+            while let Some(latest) = weak.upgrade() {
+                let reading = inner.idle_time()
+                    .map(|idle| (idle, Instant::now()))
+                    .map_err(|what| what.to_string());
+
+                *latest.lock().unwrap() = Some(reading);
+                drop(latest);
+
+                thread::sleep(interval);
+            }
+        });
+
+        BackgroundIdle{latest}
+    }
+}
+
+impl IdleSource for BackgroundIdle {
+    fn idle_time(&self) -> anyhow::Result<Duration> {
+        match &*self.latest.lock().unwrap() {
+            // not polled yet
+            None => Ok(Duration::ZERO),
+            Some(Ok((idle, at))) => Ok(*idle + at.elapsed()),
+            Some(Err(what)) => Err(anyhow::anyhow!("{}", what)),
+        }
+    }
+}
+
+/// One idle check during tracking. `away` is the work record the user
+/// walked away from, its `to` is not known until they are back. Once they
+/// are, returns that record ending at their return.
+pub fn check_idle(away: &mut Option<TimeRecord>, source: &dyn IdleSource,
+                  threshold: chrono::Duration, tracking: &TrackingCtx, now: DateTime<Utc>)
+                  -> anyhow::Result<Option<TimeRecord>>
+{
+    let idle = chrono::Duration::from_std(source.idle_time()?)
+        .unwrap_or(chrono::Duration::zero());
+
+    match away.take() {
+        None if idle >= threshold => {
+            if let (TrackingState::Active(uid), Some(id)) = (&tracking.state, tracking.record_id) {
+                // the session could have been started while away
+                let from = Rc::new(std::cmp::max(now - idle, *tracking.timestamp));
+
+                *away = Some(TimeRecord{id, from: from.clone(), to: from, uid: uid.clone(),
+                                        kind: RecordKind::Work,
+                                        session_id: tracking.session_id});
+            }
+        },
+        Some(record) if idle < threshold => {
+            let to = now - idle;
+
+            if to > *record.from {
+                return Ok(Some(TimeRecord{to: Rc::new(to), ..record}));
+            }
+        },
+        other => *away = other,
+    }
+
+    Ok(None)
+}
+
+/// X11 screensaver extension idle time, as reported by `xprintidle`.
+pub struct X11Idle;
+
+impl IdleSource for X11Idle {
+    fn idle_time(&self) -> anyhow::Result<Duration> {
+        let output = Command::new("xprintidle").output()?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("xprintidle failed: {}", output.status));
+        }
+
+        let millis = String::from_utf8_lossy(&output.stdout).trim().parse::<u64>()?;
+
+        Ok(Duration::from_millis(millis))
+    }
+}
+
+/// Idle hint of the logind session, set by the desktop environment or the
+/// screen locker.
+pub struct LogindIdle {
+    session: String,
+}
+
+impl LogindIdle {
+    pub fn new() -> LogindIdle {
+        LogindIdle{session: std::env::var("XDG_SESSION_ID").unwrap_or("auto".to_string())}
+    }
+}
+
+impl IdleSource for LogindIdle {
+    fn idle_time(&self) -> anyhow::Result<Duration> {
+        let output = Command::new("loginctl")
+            .args(&["show-session", &self.session, "-p", "IdleHint", "-p", "IdleSinceHint"])
+            .output()?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("loginctl failed: {}", output.status));
+        }
+
+        let mut idle = false;
+        let mut since_micros = 0;
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            match line.split_once('=') {
+                Some(("IdleHint", value)) => idle = value == "yes",
+                Some(("IdleSinceHint", value)) => since_micros = value.parse::<u64>()?,
+                _ => {},
+            }
+        }
+
+        if !idle || since_micros == 0 {
+            return Ok(Duration::ZERO);
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+
+        Ok(now.saturating_sub(Duration::from_micros(since_micros)))
+    }
+}
+
+/// Idle seconds read from a file, no file means the user is active.
+/// Lets the idle prompt be tried without walking away from the keyboard.
+pub struct FakeIdle {
+    path: PathBuf,
+}
+
+impl FakeIdle {
+    pub fn new(path: PathBuf) -> FakeIdle {
+        FakeIdle{path}
+    }
+}
+
+impl IdleSource for FakeIdle {
+    fn idle_time(&self) -> anyhow::Result<Duration> {
+        if !self.path.exists() {
+            return Ok(Duration::ZERO);
+        }
+
+        Ok(Duration::from_secs(fs::read_to_string(&self.path)?.trim().parse::<u64>()?))
+    }
+}
+
+/// Source named by the `idle_source` setting: "x11", "logind", "fake" (reads
+/// the file "idle" in `dir`), "auto" for the first one that works or "none".
+/// It is polled every `interval` in the background.
+pub fn make_source(name: &str, dir: &PathBuf, interval: Duration) -> Option<Box<dyn IdleSource>> {
+    let source: Box<dyn IdleSource + Send> = match name {
+        "none" => return None,
+        "x11" => Box::new(X11Idle),
+        "logind" => Box::new(LogindIdle::new()),
+        "fake" => {
+            let mut path = dir.clone();
+            path.push("idle");
+            Box::new(FakeIdle::new(path))
+        },
+        "auto" => {
+            let candidates: Vec<Box<dyn IdleSource + Send>> =
+                vec![Box::new(X11Idle), Box::new(LogindIdle::new())];
+
+            match candidates.into_iter().find(|s| s.idle_time().is_ok()) {
+                Some(source) => source,
+                None => return None,
+            }
+        },
+        _ => {
+            println!("config error: unknown idle source '{}'", name);
+            return None;
+        },
+    };
+
+    if let Err(what) = source.idle_time() {
+        println!("idle error: {}", what);
+        return None;
+    }
+
+    Some(Box::new(BackgroundIdle::new(source, interval)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use druid::TimerToken;
+
+    use crate::test_util::TempDir;
+
+    /// Fake source over its own file, `set` writes the idle seconds.
+    struct Fixture {
+        _dir: TempDir,
+        path: PathBuf,
+        source: FakeIdle,
+    }
+
+    impl Fixture {
+        fn new() -> Fixture {
+            let dir = TempDir::new();
+            let path = dir.join("idle");
+            Fixture{_dir: dir, source: FakeIdle::new(path.clone()), path}
+        }
+
+        fn set(&self, seconds: u64) {
+            fs::write(&self.path, seconds.to_string()).unwrap();
+        }
+    }
+
+    fn tracking(state: TrackingState, started: DateTime<Utc>) -> TrackingCtx {
+        TrackingCtx{state,
+                    timestamp: Rc::new(started),
+                    timer_id: Rc::new(TimerToken::INVALID),
+                    elapsed: Rc::new(chrono::Duration::zero()),
+                    record_id: Some(7),
+                    session_id: Some(3),
+                    cycle: 0,
+                    auto_run: 0,
+                    extended: Rc::new(chrono::Duration::zero())}
+    }
+
+    fn threshold() -> chrono::Duration {
+        chrono::Duration::minutes(5)
+    }
+
+    #[test]
+    fn no_file_is_active() {
+        let fixture = Fixture::new();
+
+        assert_eq!(fixture.source.idle_time().unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn short_idle_is_ignored() {
+        let fixture = Fixture::new();
+        let now = Utc::now();
+        let ctx = tracking(TrackingState::Active("a".to_string()), now - chrono::Duration::hours(1));
+        let mut away = None;
+
+        fixture.set(60);
+
+        assert!(check_idle(&mut away, &fixture.source, threshold(), &ctx, now).unwrap().is_none());
+        assert!(away.is_none());
+    }
+
+    #[test]
+    fn away_and_back() {
+        let fixture = Fixture::new();
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let ctx = tracking(TrackingState::Active("a".to_string()), start);
+        let mut away = None;
+
+        let now = start + chrono::Duration::minutes(30);
+        fixture.set(600);
+
+        assert!(check_idle(&mut away, &fixture.source, threshold(), &ctx, now).unwrap().is_none());
+        assert_eq!(*away.as_ref().unwrap().from, now - chrono::Duration::minutes(10));
+
+        // still away
+        let now = now + chrono::Duration::minutes(5);
+        fixture.set(900);
+
+        assert!(check_idle(&mut away, &fixture.source, threshold(), &ctx, now).unwrap().is_none());
+        assert!(away.is_some());
+
+        let now = now + chrono::Duration::minutes(5);
+        fixture.set(30);
+
+        let record = check_idle(&mut away, &fixture.source, threshold(), &ctx, now)
+            .unwrap().unwrap();
+
+        assert!(away.is_none());
+        assert_eq!(record.id, 7);
+        assert_eq!(record.uid, "a");
+        assert_eq!(record.kind, RecordKind::Work);
+        assert_eq!(*record.from, start + chrono::Duration::minutes(20));
+        assert_eq!(*record.to, now - chrono::Duration::seconds(30));
+    }
+
+    #[test]
+    fn away_starts_with_the_session() {
+        let fixture = Fixture::new();
+        let start = Utc::now();
+        let ctx = tracking(TrackingState::Active("a".to_string()), start);
+        let mut away = None;
+
+        let now = start + chrono::Duration::minutes(6);
+        fixture.set(3600);
+
+        check_idle(&mut away, &fixture.source, threshold(), &ctx, now).unwrap();
+
+        assert_eq!(*away.unwrap().from, start);
+    }
+
+    #[test]
+    fn only_work_is_watched() {
+        let fixture = Fixture::new();
+        let now = Utc::now();
+        let mut away = None;
+
+        fixture.set(3600);
+
+        for state in [TrackingState::Inactive, TrackingState::Paused("a".to_string()),
+                          TrackingState::Break("a".to_string())] {
+            let ctx = tracking(state, now - chrono::Duration::hours(2));
+
+            assert!(check_idle(&mut away, &fixture.source, threshold(), &ctx, now)
+                    .unwrap().is_none());
+            assert!(away.is_none());
+        }
+    }
+
+    #[test]
+    fn background_source_ages_the_reading() {
+        let fixture = Fixture::new();
+        fixture.set(400);
+
+        let source = BackgroundIdle::new(Box::new(FakeIdle::new(fixture.path.clone())),
+                                         Duration::from_secs(3600));
+
+        let start = Instant::now();
+        while source.idle_time().unwrap() == Duration::ZERO &&
+            start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(source.idle_time().unwrap() >= Duration::from_secs(400));
+    }
+}
@@ -0,0 +1,258 @@
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use druid::{ExtEventSink, Target};
+use serde::{Serialize, Deserialize};
+
+use crate::app_model::*;
+use crate::common::COMMAND_IPC_SNAPSHOT;
+use crate::task::{SessionId, TaskStatus};
+
+#[cfg(unix)]
+pub use self::unix::*;
+
+/// Task as seen by clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub uid: String,
+    pub name: String,
+    pub status: String,
+}
+
+/// Tracking state as seen by clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    /// "inactive", "active", "paused" or "break"
+    pub state: String,
+    pub task: Option<TaskInfo>,
+    /// the status bar line
+    pub text: String,
+    /// pomodoro of the state, kept in the app
+    #[serde(skip)]
+    pub session_id: Option<SessionId>,
+}
+
+/// Answer to one request line, sent back as one line of json.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Reply {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tasks: Option<Vec<TaskInfo>>,
+}
+
+/// What the socket server needs to know of the app model, taken in the UI
+/// thread.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub status: Status,
+    pub tasks: Vec<TaskInfo>,
+}
+
+/// Control socket in the config directory `dir`.
+pub fn default_path(dir: &PathBuf) -> PathBuf {
+    let mut path = dir.clone();
+    path.push("netupi.sock");
+    path
+}
+
+fn task_info(model: &AppModel, uid: &String) -> Option<TaskInfo> {
+    model.tasks.get(uid).map(|task| TaskInfo{uid: task.uid.clone(), name: task.name.clone(),
+                                             status: task.task_status.to_string().to_owned()})
+}
+
+/// The UI thread may be busy, but not for this long.
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Snapshot taken by the UI thread for a thread outside of it.
+pub fn query_snapshot(sink: &ExtEventSink) -> anyhow::Result<Snapshot> {
+    let (tx, rx) = channel();
+
+    sink.submit_command(COMMAND_IPC_SNAPSHOT, tx, Target::Auto)?;
+
+    Ok(rx.recv_timeout(SNAPSHOT_TIMEOUT)?)
+}
+
+pub fn snapshot(model: &AppModel) -> Snapshot {
+    let state = match model.tracking.state {
+        TrackingState::Inactive => "inactive",
+        TrackingState::Active(_) => "active",
+        TrackingState::Paused(_) => "paused",
+        TrackingState::Break(_) => "break",
+    };
+
+    let status = Status{state: state.to_string(),
+                        task: model.tracking.state.uid().and_then(|uid| task_info(model, uid)),
+                        text: get_status_string(model),
+                        session_id: model.tracking.session_id};
+
+    let tasks = model.tasks.values()
+        .filter(|t| t.task_status != TaskStatus::Archived)
+        .filter_map(|t| task_info(model, &t.uid))
+        .collect();
+
+    Snapshot{status, tasks}
+}
+
+/// Task named by a client: its uid or its name, ignoring case.
+#[cfg(unix)]
+fn find_task(tasks: &[TaskInfo], name: &str) -> anyhow::Result<String> {
+    if let Some(task) = tasks.iter().find(|t| t.uid == name) {
+        return Ok(task.uid.clone());
+    }
+
+    let found: Vec<&TaskInfo> = tasks.iter()
+        .filter(|t| t.name.to_lowercase() == name.to_lowercase())
+        .collect();
+
+    match found.as_slice() {
+        [task] => Ok(task.uid.clone()),
+        [] => Err(anyhow::anyhow!("no task '{}'", name)),
+        _ => Err(anyhow::anyhow!("more than one task '{}', use the uid", name)),
+    }
+}
+
+/// Line protocol over a Unix domain socket. A request is one line:
+/// `start <task>`, `pause`, `resume`, `stop`, `status` or `list`, the reply
+/// is one line of json, see `Reply`.
+#[cfg(unix)]
+mod unix {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::thread;
+
+    use druid::{ExtEventSink, Target};
+
+    use crate::common::*;
+    use super::*;
+
+    /// Socket served by `serve`, removed when dropped.
+    pub struct Server {
+        path: PathBuf,
+    }
+
+    impl Drop for Server {
+        fn drop(&mut self) {
+            if let Err(what) = fs::remove_file(&self.path) {
+                println!("ipc error: {}", what);
+            }
+        }
+    }
+
+    /// Accept clients on `path` in the background, their requests go to the
+    /// app through `sink` as the commands of the task list. The socket lives
+    /// as long as the returned `Server`.
+    pub fn serve(path: PathBuf, sink: ExtEventSink) -> anyhow::Result<Server> {
+        if let Ok(meta) = fs::symlink_metadata(&path) {
+            if !meta.file_type().is_socket() {
+                return Err(anyhow::anyhow!("{:?} is not a socket", path));
+            }
+
+            // a socket left by a crashed run refuses connections
+            if UnixStream::connect(&path).is_ok() {
+                return Err(anyhow::anyhow!("{:?} is used by another netupi", path));
+            }
+
+            fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+
+        println!("ipc listening | {:?}", path);
+
+        let server = Server{path};
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(what) => {
+                        println!("ipc error: {}", what);
+                        continue;
+                    }
+                };
+
+                let sink = sink.clone();
+
+                thread::spawn(move || {
+                    if let Err(what) = serve_client(stream, &sink) {
+                        println!("ipc error: {}", what);
+                    }
+                });
+            }
+        });
+
+        Ok(server)
+    }
+
+    fn serve_client(stream: UnixStream, sink: &ExtEventSink) -> anyhow::Result<()> {
+        let mut writer = stream.try_clone()?;
+
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let reply = run_request(line.trim(), sink).unwrap_or_else(|what| {
+                Reply{ok: false, error: Some(what.to_string()), ..Reply::default()}
+            });
+
+            writeln!(writer, "{}", serde_json::to_string(&reply)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the request against the current state, so that a command the
+    /// task list can't carry out is answered with an error.
+    fn run_request(line: &str, sink: &ExtEventSink) -> anyhow::Result<Reply> {
+        let (verb, arg) = line.split_once(' ').map_or((line, ""), |(v, a)| (v, a.trim()));
+        let snapshot = query_snapshot(sink)?;
+        let state = snapshot.status.state.as_str();
+
+        match verb {
+            "start" if arg.is_empty() => return Err(anyhow::anyhow!("start needs a task")),
+            "start" => sink.submit_command(COMMAND_TASK_START, find_task(&snapshot.tasks, arg)?,
+                                           Target::Auto)?,
+            "pause" if state == "active" => sink.submit_command(COMMAND_TASK_PAUSE, (),
+                                                                Target::Auto)?,
+            "pause" => return Err(anyhow::anyhow!("no work is tracked")),
+            "resume" if state == "paused" => {
+                let uid = snapshot.status.task.map(|t| t.uid).unwrap_or_default();
+                sink.submit_command(COMMAND_TASK_RESUME, uid, Target::Auto)?
+            },
+            "resume" => return Err(anyhow::anyhow!("nothing is paused")),
+            "stop" if state != "inactive" => sink.submit_command(COMMAND_TASK_STOP, (),
+                                                                 Target::Auto)?,
+            "stop" => return Err(anyhow::anyhow!("nothing is tracked")),
+            "status" => return Ok(Reply{ok: true, status: Some(snapshot.status),
+                                        ..Reply::default()}),
+            "list" => return Ok(Reply{ok: true, tasks: Some(snapshot.tasks),
+                                      ..Reply::default()}),
+            _ => return Err(anyhow::anyhow!("unknown request '{}'", verb)),
+        }
+
+        Ok(Reply{ok: true, ..Reply::default()})
+    }
+
+    /// Send one request line to the netupi listening on `path` and wait for
+    /// its reply.
+    pub fn request(path: &Path, line: &str) -> anyhow::Result<Reply> {
+        let mut stream = UnixStream::connect(path)?;
+        writeln!(stream, "{}", line.trim())?;
+
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+
+        Ok(serde_json::from_str(&reply)?)
+    }
+}
@@ -10,3 +10,17 @@ pub mod common;
 pub mod utils;
 pub mod time;
 pub mod widgets;
+pub mod prompt;
+pub mod time_entry;
+pub mod config;
+pub mod idle;
+pub mod theme;
+pub mod settings;
+pub mod sound;
+pub mod notification;
+pub mod break_overlay;
+pub mod mini_window;
+pub mod ipc;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,174 @@
+use std::f64::consts::PI;
+
+use druid::kurbo::{Arc, Circle, Vec2};
+use druid::widget::prelude::*;
+use druid::widget::{Button, Controller, CrossAxisAlignment, Flex, Label, LineBreaking, Painter};
+use druid::{Color, LocalizedString, Point, Target, TimerToken, Widget, WidgetExt, WindowDesc};
+
+use crate::app_model::*;
+use crate::common::*;
+use crate::theme;
+
+/// Small window on top of the others with the running session, opened and
+/// closed from the File menu.
+pub fn mini_window() -> WindowDesc<AppModel> {
+    WindowDesc::new(theme::themed(mini_widget()))
+        .window_size((420.0, 130.0))
+        .resizable(false)
+        .set_always_on_top(true)
+        .title(LocalizedString::new("netupi-mini-title").with_placeholder("netupi timer"))
+}
+
+/// Part of the work interval or break still to go, 0.0 to 1.0, `None`
+/// without a timer.
+fn get_progress_left(model: &AppModel) -> Option<f64> {
+    let total = match model.tracking.state {
+        TrackingState::Active(ref uid) | TrackingState::Paused(ref uid)
+            if is_stopwatch(model, uid) => return None,
+        TrackingState::Active(ref uid) => get_work_interval(model, uid),
+        TrackingState::Paused(ref uid) => {
+            let left = get_work_interval(model, uid) - *model.tracking.elapsed;
+            return Some(fraction(&left, &get_work_interval(model, uid)));
+        },
+        TrackingState::Break(ref uid) => get_rest_interval(model, uid),
+        TrackingState::Inactive => return None,
+    };
+
+    get_remaining_time(model).map(|left| fraction(&left, &total))
+}
+
+fn fraction(part: &chrono::Duration, total: &chrono::Duration) -> f64 {
+    if total.num_milliseconds() <= 0 {
+        return 0.0;
+    }
+
+    (part.num_milliseconds() as f64 / total.num_milliseconds() as f64).max(0.0).min(1.0)
+}
+
+fn state_color(model: &AppModel, env: &Env) -> Color {
+    match model.tracking.state {
+        TrackingState::Active(_) if get_overtime(model).is_some() =>
+            env.get(TASK_OVERTIME_COLOR_BG),
+        TrackingState::Active(_) => env.get(TASK_ACTIVE_COLOR_BG),
+        TrackingState::Paused(_) => env.get(TASK_PAUSE_COLOR_BG),
+        TrackingState::Break(_) => env.get(TASK_REST_COLOR_BG),
+        TrackingState::Inactive => env.get(TASK_COLOR_BG),
+    }
+}
+
+/// Ring emptying as the work interval or the break runs out.
+fn progress_ring() -> impl Widget<AppModel> {
+    Painter::new(|ctx: &mut PaintCtx, model: &AppModel, env| {
+        let size = ctx.size();
+        let center = Point::new(size.width / 2.0, size.height / 2.0);
+        let radius = size.width.min(size.height) / 2.0 - 5.0;
+
+        ctx.stroke(Circle::new(center, radius), &env.get(TASK_COLOR_BG), 6.0);
+
+        let left = match get_progress_left(model) {
+            Some(left) => left,
+            // overtime and stopwatch have nothing to count down
+            None if matches!(model.tracking.state, TrackingState::Inactive) => return,
+            None => 1.0,
+        };
+
+        let arc = Arc {
+            center,
+            radii: Vec2::new(radius, radius),
+            start_angle: -PI / 2.0,
+            sweep_angle: 2.0 * PI * left,
+            x_rotation: 0.0,
+        };
+
+        ctx.stroke(arc, &state_color(model, env), 6.0);
+    })
+        .fix_size(90.0, 90.0)
+}
+
+fn mini_widget() -> impl Widget<AppModel> {
+    let name = Label::new(|model: &AppModel, _env: &Env| {
+        match model.tracking.state.uid().and_then(|uid| model.tasks.get(uid)) {
+            Some(task) => task.name.clone(),
+            None => "Not tracking".to_string(),
+        }
+    })
+        .with_font(FONT_CAPTION_DESCR)
+        .with_line_break_mode(LineBreaking::Clip);
+
+    let status = Label::new(|model: &AppModel, _env: &Env| get_status_string(model))
+        .with_line_break_mode(LineBreaking::WordWrap);
+
+    // the tracking lives in the task list of the main window
+    let pause = Button::dynamic(|model: &AppModel, _env: &Env| match model.tracking.state {
+        TrackingState::Paused(_) => "Resume".to_string(),
+        _ => "Pause".to_string(),
+    })
+        .on_click(|ctx, model: &mut AppModel, _env| match model.tracking.state {
+            TrackingState::Active(_) =>
+                ctx.submit_command(COMMAND_TASK_PAUSE.with(()).to(Target::Global)),
+            TrackingState::Paused(ref uid) =>
+                ctx.submit_command(COMMAND_TASK_RESUME.with(uid.clone()).to(Target::Global)),
+            _ => {},
+        })
+        .disabled_if(|model: &AppModel, _env| !matches!(model.tracking.state,
+                                                         TrackingState::Active(_) |
+                                                         TrackingState::Paused(_)));
+
+    let stop = Button::new("Stop")
+        .on_click(|ctx, _model: &mut AppModel, _env| {
+            ctx.submit_command(COMMAND_TASK_STOP.with(()).to(Target::Global));
+        })
+        .disabled_if(|model: &AppModel, _env| matches!(model.tracking.state,
+                                                        TrackingState::Inactive));
+
+    Flex::row()
+        .cross_axis_alignment(CrossAxisAlignment::Center)
+        .with_child(progress_ring())
+        .with_spacer(15.0)
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(name)
+                .with_spacer(5.0)
+                .with_child(status)
+                .with_spacer(10.0)
+                .with_child(
+                    Flex::row()
+                        .with_child(pause)
+                        .with_default_spacer()
+                        .with_child(stop)), 1.0)
+        .padding(10.0)
+        .controller(MiniController{timer_id: TimerToken::INVALID})
+}
+
+/// Redraws the timer every second and forgets the window once it is closed.
+struct MiniController {
+    timer_id: TimerToken,
+}
+
+impl<W: Widget<AppModel>> Controller<AppModel, W> for MiniController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppModel,
+             env: &Env) {
+        match event {
+            Event::Timer(id) if *id == self.timer_id => {
+                self.timer_id = ctx.request_timer(UI_TIMER_INTERVAL);
+                ctx.request_update();
+                ctx.request_paint();
+            },
+            Event::WindowDisconnected => {
+                data.mini_window = None;
+                child.event(ctx, event, data, env)
+            },
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+
+    fn lifecycle(&mut self, child: &mut W, ctx: &mut LifeCycleCtx, event: &LifeCycle,
+                 data: &AppModel, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.timer_id = ctx.request_timer(UI_TIMER_INTERVAL);
+        }
+
+        child.lifecycle(ctx, event, data, env)
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(not(target_os = "windows"))]
+use std::thread;
+
+use druid::{ExtEventSink, Selector};
+#[cfg(all(unix, not(target_os = "macos")))]
+use druid::Target;
+
+#[cfg(not(target_os = "windows"))]
+use notify_rust::Notification;
+#[cfg(all(unix, not(target_os = "macos")))]
+use notify_rust::{Timeout, Urgency};
+
+use crate::config::Config;
+use crate::ipc::Status;
+
+/// Command of the task list sent by a button.
+#[derive(Clone, Copy)]
+pub enum ActionCommand {
+    /// sent with the task's uid
+    Task(Selector<String>),
+    /// about the running session
+    Session(Selector),
+}
+
+/// Button of a notification, only shown where the desktop supports them.
+#[derive(Clone)]
+pub struct Action {
+    pub label: &'static str,
+    pub command: ActionCommand,
+    pub uid: String,
+}
+
+impl Action {
+    pub fn task(label: &'static str, selector: Selector<String>, uid: String) -> Action {
+        Action{label, command: ActionCommand::Task(selector), uid}
+    }
+
+    pub fn session(label: &'static str, selector: Selector, uid: String) -> Action {
+        Action{label, command: ActionCommand::Session(selector), uid}
+    }
+
+    /// Send the command unless the app went on since `shown`: a late click
+    /// must not act on another session.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn submit(&self, sink: &ExtEventSink, shown: &Status) {
+        let session = |status: &Status| (status.state.clone(), status.session_id,
+                                         status.task.as_ref().map(|t| t.uid.clone()));
+
+        match crate::ipc::query_snapshot(sink) {
+            Ok(now) if session(&now.status) == session(shown) => {},
+            Ok(_) => return,
+            Err(what) => {
+                println!("notification error: {}", what);
+                return;
+            }
+        }
+
+        let result = match self.command {
+            ActionCommand::Task(selector) =>
+                sink.submit_command(selector, self.uid.clone(), Target::Auto),
+            ActionCommand::Session(selector) => sink.submit_command(selector, (), Target::Auto),
+        };
+
+        if let Err(what) = result {
+            println!("notification error: {}", what);
+        }
+    }
+}
+
+/// Show a desktop notification unless they are turned off. A click on one
+/// of `actions` comes back to the app through `sink` as its command, as long
+/// as the app is still in the state `shown` it was in.
+///
+/// With actions a thread waits for the click until the notification goes
+/// away. Critical notifications don't expire, so each one left on screen
+/// keeps its thread; there is one per finished interval or reminder at most.
+pub fn show(config: &Config, sink: Option<ExtEventSink>, summary: &str, actions: Vec<Action>,
+            shown: Status) {
+    if !config.notifications {
+        return;
+    }
+
+    // buttons need the freedesktop notification server
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    let _ = (sink, actions, shown);
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let mut notification = Notification::new();
+        notification.summary(summary);
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            notification.timeout(match config.notification_timeout {
+                0 => Timeout::Default,
+                seconds => Timeout::Milliseconds(seconds.saturating_mul(1000)),
+            });
+
+            notification.urgency(match config.notification_urgency.as_str() {
+                "low" => Urgency::Low,
+                "critical" => Urgency::Critical,
+                _ => Urgency::Normal,
+            });
+
+            for (idx, action) in actions.iter().enumerate() {
+                notification.action(&idx.to_string(), action.label);
+            }
+        }
+
+        // waiting for a click blocks until the notification goes away
+        thread::spawn(move || {
+            let handle = match notification.show() {
+                Ok(handle) => handle,
+                Err(what) => {
+                    println!("notification error: {}", what);
+                    return;
+                }
+            };
+
+            #[cfg(all(unix, not(target_os = "macos")))]
+            if !actions.is_empty() {
+                handle.wait_for_action(|id| {
+                    let action = id.parse::<usize>().ok().and_then(|idx| actions.get(idx));
+
+                    if let (Some(action), Some(sink)) = (action, &sink) {
+                        action.submit(sink, &shown);
+                    }
+                });
+            }
+
+            #[cfg(target_os = "macos")]
+            drop(handle);
+        });
+    }
+
+    #[cfg(target_os = "windows")]
+    let _ = summary;
+}
@@ -0,0 +1,280 @@
+use std::rc::Rc;
+
+use druid::lens::{self, LensExt};
+use druid::widget::{Button, Either, Flex, Label, SizedBox, ViewSwitcher};
+use druid::{Env, LocalizedString, Menu, MenuItem, Point, Widget, WidgetExt};
+
+use chrono::prelude::*;
+
+use crate::task::*;
+use crate::app_model::*;
+use crate::common::*;
+use crate::db;
+use crate::maybe::Maybe;
+use crate::time;
+
+type PromptCtx = (AppModel, Prompt);
+
+fn format_open_record(model: &AppModel, record: &TimeRecord) -> String {
+    let name = model.tasks.get(&record.uid).map(|t| t.name.clone()).unwrap_or_default();
+    let from: DateTime<Local> = DateTime::from(*record.from);
+    let checkpoint: DateTime<Local> = DateTime::from(*record.to);
+
+    let what = match record.kind {
+        RecordKind::Work => "Session",
+        RecordKind::Break => "Break after",
+        RecordKind::Pause => "Pause of",
+    };
+
+    format!("{} '{}' started at {} was not stopped, last seen at {} ({})",
+            what, name, from.format("%d %b, %H:%M"), checkpoint.format("%d %b, %H:%M"),
+            time::format_duration(&record.to.signed_duration_since(*record.from)))
+}
+
+fn close_open_record(model: &mut AppModel, record: &TimeRecord, to: DateTime<Utc>) {
+    let record = TimeRecord{to: Rc::new(to), ..record.clone()};
+
+    if let Err(what) = db::close_time_record(model.db.clone(), &record) {
+        println!("db error: {}", what);
+    }
+
+    model.records.insert(record.id, record.clone());
+
+    if model.tasks.contains_key(&record.uid) {
+        model.rebuild_task_sum(&record.uid);
+    }
+}
+
+fn discard_open_record(model: &mut AppModel, record: &TimeRecord) {
+    if let Err(what) = db::remove_time_record(model.db.clone(), record) {
+        println!("db error: {}", what);
+    }
+}
+
+fn open_record_widget() -> impl Widget<PromptCtx> {
+    Flex::row()
+        .with_flex_child(
+            Label::new(|(model, prompt): &PromptCtx, _env: &Env| match prompt {
+                Prompt::OpenRecord(record) => format_open_record(model, record),
+                _ => "".to_string(),
+            }), 1.0)
+        .with_default_spacer()
+        .with_child(Button::new("Close at last checkpoint")
+                    .on_click(|_ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::OpenRecord(record) = prompt.clone() {
+                            close_open_record(model, &record, *record.to);
+                        }
+                        model.prompts.pop_front();
+                    }))
+        .with_default_spacer()
+        .with_child(Button::new("Close now")
+                    .on_click(|_ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::OpenRecord(record) = prompt.clone() {
+                            close_open_record(model, &record, Utc::now());
+                        }
+                        model.prompts.pop_front();
+                    }))
+        .with_default_spacer()
+        .with_child(Button::new("Discard")
+                    .on_click(|_ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::OpenRecord(record) = prompt.clone() {
+                            discard_open_record(model, &record);
+                        }
+                        model.prompts.pop_front();
+                    }))
+}
+
+fn confirm_break_widget() -> impl Widget<PromptCtx> {
+    Flex::row()
+        .with_flex_child(
+            Label::new(|(model, prompt): &PromptCtx, _env: &Env| match prompt {
+                Prompt::ConfirmBreak(uid) => format!(
+                    "'{}' pomodoro finished, take a {}?",
+                    model.tasks.get(uid).map(|t| t.name.clone()).unwrap_or_default(),
+                    if is_long_break_due(model) {"long break"} else {"break"}),
+                _ => "".to_string(),
+            }), 1.0)
+        .with_default_spacer()
+        .with_child(Button::new("Start break")
+                    .on_click(|ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::ConfirmBreak(uid) = prompt.clone() {
+                            ctx.submit_command(COMMAND_BREAK_START.with(uid));
+                        }
+                        model.prompts.pop_front();
+                    }))
+        .with_default_spacer()
+        .with_child(Button::new("Skip break")
+                    .on_click(|ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::ConfirmBreak(uid) = prompt.clone() {
+                            ctx.submit_command(COMMAND_BREAK_SKIP.with(uid));
+                        }
+                        model.prompts.pop_front();
+                    }))
+}
+
+fn format_idle(model: &AppModel, idle: &TimeRecord) -> String {
+    let name = model.tasks.get(&idle.uid).map(|t| t.name.clone()).unwrap_or_default();
+    let from: DateTime<Local> = DateTime::from(*idle.from);
+    let to: DateTime<Local> = DateTime::from(*idle.to);
+
+    format!("Away from '{}' {} - {} ({}), keep that time?",
+            name, from.format("%H:%M"), to.format("%H:%M"),
+            time::format_duration(&idle.to.signed_duration_since(*idle.from)))
+}
+
+fn make_idle_assign_menu(model: &AppModel, idle: &TimeRecord) -> Menu<AppModel> {
+    let mut result = Menu::new(LocalizedString::new("Assign to task"));
+
+    let mut tasks: Vec<&Task> = model.tasks.values()
+        .filter(|t| t.task_status != TaskStatus::Archived && !t.uid.eq(&idle.uid))
+        .collect();
+    tasks.sort();
+
+    for task in tasks {
+        let idle = idle.clone();
+        let uid = task.uid.clone();
+        result = result.entry(
+            MenuItem::new(task.name.clone()).on_activate(
+                move |ctx, data: &mut AppModel, _env| {
+                    ctx.submit_command(COMMAND_IDLE_ASSIGN.with((idle.clone(), uid.clone())));
+                    data.prompts.pop_front();
+                }));
+    }
+
+    result
+}
+
+fn idle_widget() -> impl Widget<PromptCtx> {
+    Flex::row()
+        .with_flex_child(
+            Label::new(|(model, prompt): &PromptCtx, _env: &Env| match prompt {
+                Prompt::Idle(idle) => format_idle(model, idle),
+                _ => "".to_string(),
+            }), 1.0)
+        .with_default_spacer()
+        .with_child(Button::new("Keep")
+                    .on_click(|_ctx, (model, _): &mut PromptCtx, _env| {
+                        model.prompts.pop_front();
+                    }))
+        .with_default_spacer()
+        .with_child(Button::new("Discard")
+                    .on_click(|ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::Idle(idle) = prompt.clone() {
+                            ctx.submit_command(COMMAND_IDLE_DISCARD.with(idle));
+                        }
+                        model.prompts.pop_front();
+                    }))
+        .with_default_spacer()
+        .with_child(Button::new("Assign to...")
+                    .on_click(|ctx, (model, prompt): &mut PromptCtx, _env| {
+                        // the prompt goes away once a task is picked
+                        if let Prompt::Idle(idle) = prompt {
+                            ctx.show_context_menu(make_idle_assign_menu(model, idle), Point::ZERO);
+                        }
+                    }))
+}
+
+fn format_clock_jump(model: &AppModel, gap: &TimeRecord) -> String {
+    let name = model.tasks.get(&gap.uid).map(|t| t.name.clone()).unwrap_or_default();
+    let from: DateTime<Local> = DateTime::from(*gap.from);
+    let jump = time::format_duration(&gap.to.signed_duration_since(*gap.from));
+
+    match gap.kind {
+        RecordKind::Break =>
+            format!("Suspended or clock changed for {} at {}, break after '{}' ended there",
+                    jump, from.format("%H:%M"), name),
+        _ =>
+            format!("Suspended or clock changed for {} at {}, '{}' is paused since then",
+                    jump, from.format("%H:%M"), name),
+    }
+}
+
+/// Whether the session cut by the clock jump is still paused as it was left.
+fn is_gap_paused(model: &AppModel, gap: &TimeRecord) -> bool {
+    matches!(model.tracking.state, TrackingState::Paused(ref uid) if uid.eq(&gap.uid))
+}
+
+fn clock_jump_widget() -> impl Widget<PromptCtx> {
+    let work_buttons = Flex::row()
+        .with_child(Button::new("Resume")
+                    .on_click(|ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::ClockJump(gap) = prompt.clone() {
+                            if is_gap_paused(model, &gap) {
+                                ctx.submit_command(COMMAND_TASK_RESUME.with(gap.uid));
+                            }
+                        }
+                        model.prompts.pop_front();
+                    }))
+        .with_default_spacer()
+        .with_child(Button::new("Count as work")
+                    .on_click(|ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::ClockJump(gap) = prompt.clone() {
+                            ctx.submit_command(COMMAND_CLOCK_JUMP_WORK.with(gap));
+                        }
+                        model.prompts.pop_front();
+                    }))
+        .with_default_spacer()
+        .with_child(Button::new("Stop")
+                    .on_click(|ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::ClockJump(gap) = prompt.clone() {
+                            if is_gap_paused(model, &gap) {
+                                ctx.submit_command(COMMAND_TASK_STOP.with(()));
+                            }
+                        }
+                        model.prompts.pop_front();
+                    }));
+
+    let break_buttons = Flex::row()
+        .with_child(Button::new("Start next pomodoro")
+                    .on_click(|ctx, (model, prompt): &mut PromptCtx, _env| {
+                        if let Prompt::ClockJump(gap) = prompt.clone() {
+                            if let TrackingState::Inactive = model.tracking.state {
+                                ctx.submit_command(COMMAND_TASK_START.with(gap.uid));
+                            }
+                        }
+                        model.prompts.pop_front();
+                    }))
+        .with_default_spacer()
+        .with_child(Button::new("OK")
+                    .on_click(|_ctx, (model, _): &mut PromptCtx, _env| {
+                        model.prompts.pop_front();
+                    }));
+
+    Flex::row()
+        .with_flex_child(
+            Label::new(|(model, prompt): &PromptCtx, _env: &Env| match prompt {
+                Prompt::ClockJump(gap) => format_clock_jump(model, gap),
+                _ => "".to_string(),
+            }), 1.0)
+        .with_default_spacer()
+        .with_child(Either::new(
+            |(_, prompt): &PromptCtx, _env| matches!(prompt, Prompt::ClockJump(gap)
+                                                     if gap.kind == RecordKind::Break),
+            break_buttons,
+            work_buttons))
+}
+
+/// Shows the oldest pending `AppModel::prompts` entry with its choices.
+pub fn prompt_widget() -> impl Widget<AppModel> {
+    Maybe::new(
+        || ViewSwitcher::new(
+            |(_, prompt): &PromptCtx, _env| prompt.clone(),
+            |prompt: &Prompt, _data: &PromptCtx, _env| match prompt {
+                Prompt::OpenRecord(_) => open_record_widget().boxed(),
+                Prompt::ConfirmBreak(_) => confirm_break_widget().boxed(),
+                Prompt::Idle(_) => idle_widget().boxed(),
+                Prompt::ClockJump(_) => clock_jump_widget().boxed(),
+            })
+            .padding(10.0)
+            .border(TASK_ACTIVE_COLOR_BG, 1.0),
+        || SizedBox::empty(),
+    )
+    .lens(lens::Identity.map(
+        |d: &AppModel| d.prompts.front().map(|p| (d.clone(), p.clone())),
+        |d: &mut AppModel, x: Option<PromptCtx>| {
+            if let Some((model, _)) = x {
+                *d = model;
+            }
+        },
+    ))
+}
@@ -0,0 +1,171 @@
+use std::ops::RangeInclusive;
+
+use druid::lens::LensExt;
+use druid::widget::{Button, Checkbox, CrossAxisAlignment, Flex, Label, Radio, Scroll, Slider,
+                    TextBox};
+use druid::{commands, Data, Lens, LocalizedString, TextAlignment, Widget, WidgetExt, WindowDesc};
+
+use crate::app_model::*;
+use crate::common::*;
+use crate::config::{self, Config};
+use crate::editable_label::EditableLabel;
+use crate::theme;
+
+pub fn settings_window() -> WindowDesc<AppModel> {
+    WindowDesc::new(theme::themed(settings_widget()))
+        .window_size((500.0, 750.0))
+        .title(LocalizedString::new("netupi-settings-title").with_placeholder("Settings"))
+}
+
+const SOUND_HINT: &str = "notif, bell, none or a file";
+
+fn caption(text: &str) -> impl Widget<AppModel> {
+    Label::new(text).with_font(FONT_CAPTION_DESCR)
+}
+
+/// Setting `field` edited as text, `unit` follows the value. Values out of
+/// `range` are taken as its nearest end.
+fn number_row<T, L>(text: &str, field: L, unit: &str, range: RangeInclusive<T>)
+                    -> impl Widget<AppModel>
+where T: Data + Copy + PartialOrd + std::fmt::Display + std::str::FromStr,
+      L: Lens<Config, T> + 'static
+{
+    Flex::row()
+        .with_child(Label::new(text).fix_width(220.0))
+        .with_child(EditableLabel::parse()
+                    .with_text_alignment(TextAlignment::End)
+                    .lens(AppModel::settings.then(field)
+                          .map(|value: &T| *value, move |value: &mut T, new: T| *value = config::clamp(new, &range)))
+                    .fix_width(60.0))
+        .with_default_spacer()
+        .with_child(Label::new(unit))
+}
+
+fn text_row(text: &str, field: impl Lens<Config, String> + 'static, placeholder: &str)
+             -> impl Widget<AppModel> {
+    Flex::row()
+        .with_child(Label::new(text).fix_width(220.0))
+        .with_flex_child(TextBox::new()
+                         .with_placeholder(placeholder.to_string())
+                         .expand_width()
+                         .lens(AppModel::settings.then(field)), 1.0)
+}
+
+fn check_row(text: &str, field: impl Lens<Config, bool> + 'static) -> impl Widget<AppModel> {
+    Checkbox::new(text).lens(AppModel::settings.then(field))
+}
+
+fn save_settings(model: &mut AppModel) {
+    model.config = model.settings.clone();
+
+    if let Err(what) = config::save(&model.config_path, &model.config) {
+        println!("config error: {}", what);
+    }
+}
+
+/// Changes apply on "Save", which writes them to the config file, "Close"
+/// drops them.
+fn settings_widget() -> impl Widget<AppModel> {
+    let column = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(caption("New tasks"))
+        .with_default_spacer()
+        .with_child(number_row("Work",
+                               Config::default_work_minutes, "min", config::DURATION_MINUTES))
+        .with_child(number_row("Break",
+                               Config::default_break_minutes, "min", config::DURATION_MINUTES))
+        .with_spacer(15.0)
+        .with_child(caption("Pomodoros"))
+        .with_default_spacer()
+        .with_child(number_row("Long break after",
+                               Config::long_break_cycle, "pomodoros", config::CYCLES))
+        .with_child(number_row("Long break",
+                               Config::long_break_minutes, "min", config::DURATION_MINUTES))
+        .with_child(number_row("New cycle after a pause of", Config::cycle_reset_minutes, "min",
+                               config::WAIT_MINUTES))
+        .with_default_spacer()
+        .with_child(check_row("Start the next pomodoro after a break", Config::auto_start_work))
+        .with_child(number_row("Pomodoros in a row, 0 for no limit",
+                               Config::auto_cycles, "", config::CYCLES))
+        .with_child(check_row("Skip breaks", Config::skip_breaks))
+        .with_child(check_row("Ask before a break", Config::confirm_break))
+        .with_child(check_row("Flow mode, go on as overtime", Config::flow_mode))
+        .with_child(check_row("Stopwatch for all tasks", Config::stopwatch))
+        .with_spacer(15.0)
+        .with_child(caption("Breaks"))
+        .with_default_spacer()
+        .with_child(check_row("Cover the screen during breaks", Config::break_overlay))
+        .with_child(number_row("Allow skipping a break after", Config::break_skip_delay_seconds,
+                               "s", config::DELAY_SECONDS))
+        .with_child(check_row("Type 'skip' to skip a break", Config::break_skip_confirm))
+        .with_spacer(15.0)
+        .with_child(caption("Sound and notifications"))
+        .with_default_spacer()
+        .with_child(check_row("Sound", Config::sound))
+        .with_child(text_row("Pomodoro finished", Config::sound_work_finished, SOUND_HINT))
+        .with_child(text_row("Break finished", Config::sound_break_finished, SOUND_HINT))
+        .with_child(text_row("Pomodoro started", Config::sound_session_start, SOUND_HINT))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Volume").fix_width(220.0))
+                .with_child(Slider::new().with_range(0.0, 1.0)
+                            .lens(AppModel::settings.then(Config::volume))))
+        .with_child(text_row("While working", Config::ambient_sound,
+                              "tick, white, brown, none or a file"))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Volume while working").fix_width(220.0))
+                .with_child(Slider::new().with_range(0.0, 1.0)
+                            .lens(AppModel::settings.then(Config::ambient_volume))))
+        .with_child(check_row("Desktop notifications", Config::notifications))
+        .with_child(number_row("Notifications stay for, 0 for default",
+                               Config::notification_timeout, "s", config::TIMEOUT_SECONDS))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Urgency").fix_width(220.0))
+                .with_child(Radio::new("low", "low".to_string()))
+                .with_child(Radio::new("normal", "normal".to_string()))
+                .with_child(Radio::new("critical", "critical".to_string()))
+                .lens(AppModel::settings.then(Config::notification_urgency)))
+        .with_spacer(15.0)
+        .with_child(caption("Appearance"))
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(Radio::new("dark", "dark".to_string()))
+                .with_child(Radio::new("light", "light".to_string()))
+                .lens(AppModel::settings.then(Config::theme)))
+        .with_child(number_row("Font size", Config::font_size, "", config::FONT_SIZE))
+        .with_spacer(15.0)
+        .with_child(caption("Idle time"))
+        .with_default_spacer()
+        .with_child(number_row("Ask after being away for",
+                               Config::idle_minutes, "min", config::WAIT_MINUTES))
+        .with_child(Label::new("The idle source is picked on start"))
+        .with_spacer(15.0)
+        .with_child(caption("Reminders"))
+        .with_default_spacer()
+        .with_child(number_row("Remind when nothing is tracked for, 0 for never",
+                               Config::reminder_minutes, "min", config::WAIT_MINUTES))
+        .with_child(text_row("Working hours from", Config::work_hours_start, "HH:MM"))
+        .with_child(text_row("Working hours until", Config::work_hours_end, "HH:MM"))
+        .with_child(check_row("Remind on weekends", Config::reminder_weekends));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_flex_child(Scroll::new(column).vertical(), 1.0)
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(Button::new("Save")
+                            .on_click(|ctx, model: &mut AppModel, _env| {
+                                save_settings(model);
+                                ctx.submit_command(commands::CLOSE_WINDOW);
+                            }))
+                .with_default_spacer()
+                .with_child(Button::new("Close")
+                            .on_click(|ctx, _model: &mut AppModel, _env| {
+                                ctx.submit_command(commands::CLOSE_WINDOW);
+                            })))
+        .padding(15.0)
+}
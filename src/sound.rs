@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+
+use crate::common::*;
+
+#[derive(Clone)]
+pub enum Sound {
+    Bundled(&'static [u8]),
+    /// ogg, wav, mp3 or flac file
+    File(PathBuf),
+}
+
+impl Sound {
+    /// Sound named by a setting: "notif" or "bell" for the bundled ones,
+    /// "none" or "" for silence, anything else is a file, relative paths
+    /// starting from `dir`.
+    pub fn from_setting(name: &str, dir: &Path) -> Option<Sound> {
+        match name.trim() {
+            "" | "none" => None,
+            "notif" => Some(Sound::Bundled(SOUND_NOTIF)),
+            "bell" => Some(Sound::Bundled(SOUND_BELL)),
+            path => Some(Sound::File(dir.join(path))),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Sound::Bundled(_) => "bundled sound".to_string(),
+            Sound::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+/// Background sound looped during work.
+#[derive(Clone)]
+pub enum Ambient {
+    Tick,
+    WhiteNoise,
+    BrownNoise,
+    File(PathBuf),
+}
+
+impl Ambient {
+    /// Ambient sound named by a setting: "tick", "white", "brown", "none" or
+    /// "" for silence, anything else is a file as for `Sound::from_setting`.
+    pub fn from_setting(name: &str, dir: &Path) -> Option<Ambient> {
+        match name.trim() {
+            "" | "none" => None,
+            "tick" => Some(Ambient::Tick),
+            "white" => Some(Ambient::WhiteNoise),
+            "brown" => Some(Ambient::BrownNoise),
+            path => Some(Ambient::File(dir.join(path))),
+        }
+    }
+}
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// Generated mono sound: noise, or a short noise burst once a second.
+struct Synth {
+    kind: Ambient,
+    rng: u32,
+    brown: f32,
+    pos: u32,
+}
+
+impl Synth {
+    fn new(kind: Ambient) -> Synth {
+        Synth{kind, rng: 0x2545_f491, brown: 0.0, pos: 0}
+    }
+
+    fn white(&mut self) -> f32 {
+        // xorshift32
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+impl Iterator for Synth {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let white = self.white();
+        self.pos = (self.pos + 1) % SAMPLE_RATE;
+
+        Some(match self.kind {
+            Ambient::BrownNoise => {
+                self.brown = (self.brown + 0.02 * white) / 1.02;
+                self.brown * 3.5
+            },
+            Ambient::Tick => {
+                let click = SAMPLE_RATE / 200;
+                if self.pos < click {
+                    white * (1.0 - self.pos as f32 / click as f32)
+                } else {
+                    0.0
+                }
+            },
+            _ => white * 0.5,
+        })
+    }
+}
+
+impl Source for Synth {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+enum AudioCommand {
+    Play(Sound, f32),
+    StartAmbient(Ambient, f32),
+    AmbientVolume(f32),
+    StopAmbient,
+}
+
+fn play_sound(handle: &OutputStreamHandle, sound: &Sound, volume: f32) -> anyhow::Result<()> {
+    let sink = Sink::try_new(handle)?;
+    sink.set_volume(volume);
+
+    match sound {
+        Sound::Bundled(bytes) => sink.append(Decoder::new(Cursor::new(*bytes))?),
+        Sound::File(path) => sink.append(Decoder::new(BufReader::new(File::open(path)?))?),
+    }
+
+    sink.detach();
+
+    Ok(())
+}
+
+fn start_ambient(handle: &OutputStreamHandle, ambient: &Ambient, volume: f32)
+                 -> anyhow::Result<Sink>
+{
+    let sink = Sink::try_new(handle)?;
+    sink.set_volume(volume);
+
+    match ambient {
+        Ambient::File(path) =>
+            sink.append(Decoder::new(BufReader::new(File::open(path)?))?.repeat_infinite()),
+        kind => sink.append(Synth::new(kind.clone())),
+    }
+
+    Ok(sink)
+}
+
+/// Owner of the audio output, living in its own thread for the whole run.
+/// One-shot sounds overlap freely, at most one ambient sound loops until
+/// stopped.
+pub struct AudioController {
+    tx: Sender<AudioCommand>,
+}
+
+impl AudioController {
+    pub fn new() -> AudioController {
+        let (tx, rx) = channel::<AudioCommand>();
+
+        thread::spawn(move || {
+            // the output stream stops when dropped and can't leave this thread
+            let (_stream, handle) = match OutputStream::try_default() {
+                Ok(output) => output,
+                Err(what) => {
+                    println!("sound error: {}", what);
+                    return;
+                }
+            };
+
+            let mut ambient: Option<Sink> = None;
+
+            for command in rx {
+                match command {
+                    AudioCommand::Play(sound, volume) => {
+                        if let Err(what) = play_sound(&handle, &sound, volume) {
+                            println!("sound error: {}: {}", sound.describe(), what);
+                        }
+                    },
+                    AudioCommand::StartAmbient(kind, volume) => {
+                        ambient = match start_ambient(&handle, &kind, volume) {
+                            Ok(sink) => Some(sink),
+                            Err(what) => {
+                                println!("sound error: ambient: {}", what);
+                                None
+                            }
+                        };
+                    },
+                    AudioCommand::AmbientVolume(volume) => {
+                        if let Some(ref sink) = ambient {
+                            sink.set_volume(volume);
+                        }
+                    },
+                    // dropping the sink stops it
+                    AudioCommand::StopAmbient => ambient = None,
+                }
+            }
+        });
+
+        AudioController{tx}
+    }
+
+    fn send(&self, command: AudioCommand) {
+        // without an audio device the thread is gone, that was reported
+        let _ = self.tx.send(command);
+    }
+
+    /// Play `sound` once, a file that can't be opened or decoded is
+    /// reported and skipped.
+    pub fn play(&self, sound: Sound, volume: f32) {
+        self.send(AudioCommand::Play(sound, volume));
+    }
+
+    /// Loop `ambient` in place of the one playing, if any.
+    pub fn start_ambient(&self, ambient: Ambient, volume: f32) {
+        self.send(AudioCommand::StartAmbient(ambient, volume));
+    }
+
+    pub fn set_ambient_volume(&self, volume: f32) {
+        self.send(AudioCommand::AmbientVolume(volume));
+    }
+
+    pub fn stop_ambient(&self) {
+        self.send(AudioCommand::StopAmbient);
+    }
+}
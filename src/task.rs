@@ -1,5 +1,6 @@
 use core::cmp::Ordering;
-use std::ops::Add;
+use std::iter::FromIterator;
+use std::ops::{Add, Bound};
 use std::rc::Rc;
 use std::time::SystemTime;
 
@@ -16,8 +17,10 @@ use uuid::Uuid;
 
 pub type TagSet        = OrdSet<String>;
 pub type TaskMap       = OrdMap<String, Task>;
-pub type TimeRecordMap = OrdMap<DateTime<Utc>, TimeRecord>;
-pub type TimeRecordSet = OrdSet<DateTime<Utc>>;
+pub type RecordId      = i64;
+pub type TimeRecordSet = OrdSet<RecordId>;
+pub type SessionId     = i64;
+pub type SessionCounts = OrdMap<String, u32>;
 pub type TimePrefixSum = OrdMap<DateTime<Utc>, TimePrefix>;
 pub type TaskSums      = OrdMap::<String, TimePrefixSum>;
 
@@ -42,6 +45,66 @@ impl TaskStatus {
     }
 }
 
+/// What a time record stands for, only work counts toward task totals.
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum RecordKind {
+    Work,
+    Break,
+    Pause,
+}
+
+impl RecordKind {
+    pub fn to_string(&self) -> &str {
+        match &self {
+            RecordKind::Work  => "Work",
+            RecordKind::Break => "Break",
+            RecordKind::Pause => "Pause",
+        }
+    }
+
+    pub fn from_str(src: &str) -> Option<RecordKind> {
+        match src {
+            "Work"  => Some(RecordKind::Work),
+            "Break" => Some(RecordKind::Break),
+            "Pause" => Some(RecordKind::Pause),
+            _ => None,
+        }
+    }
+}
+
+/// How a pomodoro session ended.
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum SessionOutcome {
+    Running,
+    /// ran until the work timer fired
+    Completed,
+    /// stopped early
+    Stopped,
+    /// left for another task
+    Switched,
+}
+
+impl SessionOutcome {
+    pub fn to_string(&self) -> &str {
+        match &self {
+            SessionOutcome::Running   => "Running",
+            SessionOutcome::Completed => "Completed",
+            SessionOutcome::Stopped   => "Stopped",
+            SessionOutcome::Switched  => "Switched",
+        }
+    }
+
+    pub fn from_str(src: &str) -> Option<SessionOutcome> {
+        match src {
+            "Running"   => Some(SessionOutcome::Running),
+            "Completed" => Some(SessionOutcome::Completed),
+            "Stopped"   => Some(SessionOutcome::Stopped),
+            "Switched"  => Some(SessionOutcome::Switched),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Data, PartialEq)]
 pub enum CuaPriority {
     Unspecified,
@@ -89,13 +152,22 @@ pub struct Task {
     pub work_duration: Rc<Duration>,
     pub break_duration: Rc<Duration>,
     pub color: druid::Color,
+    /// count up without the pomodoro timer
+    pub stopwatch: bool,
+    /// sound setting played when a pomodoro of the task finishes, empty for
+    /// the configured one
+    pub sound: String,
 }
 
 #[derive(Debug, Clone, Data)]
 pub struct TimeRecord {
+    pub id: RecordId,
     pub from: Rc<DateTime<Utc>>,
     pub to: Rc<DateTime<Utc>>,
-    pub uid: String
+    pub uid: String,
+    pub kind: RecordKind,
+    /// pomodoro the record was tracked in, none for records added by hand
+    pub session_id: Option<SessionId>,
 }
 
 impl Ord for Task {
@@ -125,7 +197,7 @@ impl PartialEq for Task {
 }
 
 impl TimeRecord {
-    fn duration(&self) -> chrono::Duration {
+    pub fn duration(&self) -> chrono::Duration {
         self.to.signed_duration_since(*self.from)
     }
 }
@@ -141,7 +213,8 @@ impl Task {
         return Task{name, description, uid, tags, priority, task_status,
                     work_duration: Rc::new(work_duration),
                     break_duration: Rc::new(break_duration),
-                    seq, color: Color::BLACK};
+                    seq, color: Color::BLACK, stopwatch: false,
+                    sound: "".to_string()};
     }
 
     pub fn new_simple(name: String) -> Task {
@@ -244,19 +317,208 @@ pub fn get_total_time_from_sums(sums: &TaskSums, from: &DateTime::<Utc>,
 }
 
 pub fn add_record_to_sum(sum_map: &mut TimePrefixSum, record: &TimeRecord) {
-    // handle case when  record is older than newest entry in sum_map
+    if record.kind != RecordKind::Work {
+        return;
+    }
 
     if sum_map.is_empty() {
         let epoch_0 = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc);
         sum_map.insert(epoch_0, TimePrefix::new(&Duration::zero()));
     }
 
-    let last = match sum_map.get_max() {
-        Some(ref max) => max.1.clone() + TimePrefix::new(&record.duration()),
-        None => TimePrefix::new(&chrono::Duration::zero()),
+    let duration = TimePrefix::new(&record.duration());
+
+    let prev = match sum_map.get_prev(&*record.from) {
+        Some((_, prefix)) => prefix.clone(),
+        None => TimePrefix::new(&Duration::zero()),
     };
 
-    sum_map.insert(*record.from, last);
+    // record may be older than the newest entry, then every later prefix
+    // includes its duration too
+    let later: Vec<(DateTime<Utc>, TimePrefix)> = sum_map
+        .range((Bound::Excluded(*record.from), Bound::Unbounded))
+        .map(|(k, v)| (*k, v.clone()))
+        .collect();
+
+    sum_map.insert(*record.from, prev + duration.clone());
+
+    for (k, v) in later {
+        sum_map.insert(k, v + duration.clone());
+    }
+}
+
+/// Time records by id, with an index by start time kept up to date on
+/// insert and remove.
+#[derive(Debug, Clone, Default)]
+pub struct TimeRecordMap {
+    by_id: OrdMap<RecordId, TimeRecord>,
+    by_time: OrdSet<(DateTime<Utc>, RecordId)>,
+}
+
+impl Data for TimeRecordMap {
+    fn same(&self, other: &Self) -> bool {
+        // the index only changes along with the records
+        self.by_id.same(&other.by_id)
+    }
+}
+
+impl TimeRecordMap {
+    pub fn new() -> TimeRecordMap {
+        TimeRecordMap::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    pub fn get(&self, id: &RecordId) -> Option<&TimeRecord> {
+        self.by_id.get(id)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &TimeRecord> {
+        self.by_id.values()
+    }
+
+    pub fn insert(&mut self, id: RecordId, record: TimeRecord) -> Option<TimeRecord> {
+        let old = self.remove(&id);
+        self.by_time.insert((*record.from, id));
+        self.by_id.insert(id, record);
+        old
+    }
+
+    pub fn remove(&mut self, id: &RecordId) -> Option<TimeRecord> {
+        let old = self.by_id.remove(id)?;
+        self.by_time.remove(&(*old.from, *id));
+        Some(old)
+    }
+}
+
+impl FromIterator<(RecordId, TimeRecord)> for TimeRecordMap {
+    fn from_iter<I: IntoIterator<Item = (RecordId, TimeRecord)>>(iter: I) -> Self {
+        let mut result = TimeRecordMap::new();
+        for (id, record) in iter {
+            result.insert(id, record);
+        }
+        result
+    }
+}
+
+impl IntoIterator for TimeRecordMap {
+    type Item = (RecordId, TimeRecord);
+    type IntoIter = druid::im::ordmap::ConsumingIter<(RecordId, TimeRecord)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.by_id.into_iter()
+    }
+}
+
+/// Records ordered by start time, ids only break ties: they follow insertion
+/// order, not time.
+pub fn records_by_time(records: &TimeRecordMap) -> impl DoubleEndedIterator<Item = &TimeRecord>
+{
+    records.by_time.iter().filter_map(move |(_, id)| records.by_id.get(id))
+}
+
+/// Task of the latest record that is not archived.
+pub fn get_last_task(tasks: &TaskMap, records: &TimeRecordMap) -> Option<String>
+{
+    for r in records_by_time(records).rev() {
+        if let Some(t) = tasks.get(&r.uid) {
+            if t.task_status != TaskStatus::Archived {
+                return Some(t.uid.clone());
+            }
+        }
+    }
+
+    return None;
+}
+
+/// End of the latest work record, killed records excluded.
+pub fn last_work_end(records: &TimeRecordMap, killed: &TimeRecordSet) -> Option<DateTime<Utc>>
+{
+    records.values()
+        .filter(|r| !killed.contains(&r.id) && r.kind == RecordKind::Work)
+        .map(|r| *r.to)
+        .max()
+}
+
+/// First record of `kind`, or of any kind for `None`, sharing some time with
+/// `[from, to)`, killed records excluded.
+pub fn find_overlapping<'a>(records: &'a TimeRecordMap, killed: &TimeRecordSet,
+                            from: &DateTime<Utc>, to: &DateTime<Utc>, kind: Option<RecordKind>)
+                            -> Option<&'a TimeRecord>
+{
+    records_by_time(records)
+        .filter(|r| !killed.contains(&r.id) && kind.map_or(true, |kind| r.kind == kind))
+        .find(|r| *r.from < *to && *r.to > *from)
+}
+
+/// Cut `record` at `at` into two records, the second one going to `uid`.
+/// The second record has no id until it is stored.
+pub fn split_record(record: &TimeRecord, at: &DateTime<Utc>, uid: &str)
+                    -> Option<(TimeRecord, TimeRecord)>
+{
+    if *at <= *record.from || *at >= *record.to {
+        return None;
+    }
+
+    let at = Rc::new(*at);
+
+    Some((TimeRecord{id: record.id, from: record.from.clone(), to: at.clone(),
+                     uid: record.uid.clone(), kind: record.kind, session_id: record.session_id},
+          TimeRecord{id: 0, from: at, to: record.to.clone(), uid: uid.to_string(),
+                     kind: record.kind, session_id: record.session_id}))
+}
+
+/// One record for all of `records`, which must belong to the same task and
+/// be of the same kind. It starts with the earliest one, keeps its id and
+/// lasts as long as the time they cover: gaps between them (pauses) don't
+/// count, time covered twice counts once. `between` are records of other
+/// kinds lying in those gaps, they are returned moved right after the merged
+/// record, in their order.
+pub fn merge_records(records: &[TimeRecord], between: &[TimeRecord])
+                     -> Option<(TimeRecord, Vec<TimeRecord>)>
+{
+    let first = records.iter().min_by_key(|r| (*r.from, r.id))?;
+
+    if records.iter().any(|r| !r.uid.eq(&first.uid) || r.kind != first.kind) {
+        return None;
+    }
+
+    let mut sorted: Vec<&TimeRecord> = records.iter().collect();
+    sorted.sort_by_key(|r| (*r.from, r.id));
+
+    let mut worked = Duration::zero();
+    let mut covered = *first.from;
+    for r in sorted {
+        let from = std::cmp::max(*r.from, covered);
+        if *r.to > from {
+            worked = worked + r.to.signed_duration_since(from);
+            covered = *r.to;
+        }
+    }
+
+    let merged = TimeRecord{id: first.id, from: first.from.clone(),
+                            to: Rc::new(*first.from + worked),
+                            uid: first.uid.clone(), kind: first.kind,
+                            session_id: first.session_id};
+
+    let mut moved: Vec<TimeRecord> = between.to_vec();
+    moved.sort_by_key(|r| (*r.from, r.id));
+
+    let mut at = *merged.to;
+    for r in moved.iter_mut() {
+        let to = at + r.to.signed_duration_since(*r.from);
+        r.from = Rc::new(at);
+        r.to = Rc::new(to);
+        at = to;
+    }
+
+    Some((merged, moved))
 }
 
 pub fn build_time_prefix_sum(_tasks: &TaskMap, records: &TimeRecordMap, filter: String,
@@ -268,8 +530,8 @@ pub fn build_time_prefix_sum(_tasks: &TaskMap, records: &TimeRecordMap, filter:
 
     let mut psum = Duration::zero();
 
-    for (k, v) in records {
-        if killed.contains(&k) {continue;}
+    for v in records_by_time(records) {
+        if killed.contains(&v.id) || v.kind != RecordKind::Work {continue;}
 
         if filter.eq(&v.uid) {
             psum = psum + v.to.signed_duration_since(*v.from);
@@ -279,3 +541,146 @@ pub fn build_time_prefix_sum(_tasks: &TaskMap, records: &TimeRecordMap, filter:
 
     return result;
 }
+
+/// Break time taken since `from` and break time due for the work done since
+/// then, each task owes `break_duration` per `work_duration` of work.
+pub fn get_break_stats(tasks: &TaskMap, records: &TimeRecordMap, killed: &TimeRecordSet,
+                       from: &DateTime<Utc>) -> (Duration, Duration)
+{
+    let mut taken = Duration::zero();
+    let mut due = Duration::zero();
+
+    for r in records.values() {
+        if killed.contains(&r.id) || *r.from < *from {continue;}
+
+        match r.kind {
+            RecordKind::Break => taken = taken + r.duration(),
+            RecordKind::Work => {
+                if let Some(task) = tasks.get(&r.uid) {
+                    let work = task.work_duration.num_seconds();
+
+                    if work > 0 {
+                        due = due + Duration::seconds(
+                            task.break_duration.num_seconds() * r.duration().num_seconds() / work);
+                    }
+                }
+            },
+            RecordKind::Pause => {},
+        }
+    }
+
+    (taken, due)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.ymd(2022, 1, 31).and_hms(9, 0, 0) + Duration::minutes(minutes)
+    }
+
+    fn record(id: RecordId, from: i64, to: i64, kind: RecordKind) -> TimeRecord {
+        TimeRecord{id, from: Rc::new(at(from)), to: Rc::new(at(to)), uid: "a".to_string(),
+                   kind, session_id: Some(1)}
+    }
+
+    /// Start times of the index and of the records themselves agree.
+    fn assert_consistent(map: &TimeRecordMap) {
+        assert_eq!(map.by_time.len(), map.by_id.len());
+
+        for (from, id) in map.by_time.iter() {
+            assert_eq!(map.get(id).map(|r| *r.from), Some(*from));
+        }
+    }
+
+    #[test]
+    fn record_map_keeps_its_index() {
+        let mut map = TimeRecordMap::new();
+
+        map.insert(1, record(1, 30, 40, RecordKind::Work));
+        map.insert(2, record(2, 0, 10, RecordKind::Work));
+        map.insert(3, record(3, 0, 5, RecordKind::Pause));
+        assert_consistent(&map);
+
+        // moving a record moves its index entry
+        map.insert(2, record(2, 50, 60, RecordKind::Work));
+        assert_consistent(&map);
+
+        let ids: Vec<RecordId> = records_by_time(&map).map(|r| r.id).collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+
+        assert!(map.remove(&1).is_some());
+        assert!(map.remove(&1).is_none());
+        assert_consistent(&map);
+
+        let ids: Vec<RecordId> = records_by_time(&map).rev().map(|r| r.id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn merge_adjacent() {
+        let (merged, moved) = merge_records(&[record(2, 25, 50, RecordKind::Work),
+                                              record(1, 0, 25, RecordKind::Work)], &[]).unwrap();
+
+        assert_eq!(merged.id, 1);
+        assert_eq!((*merged.from, *merged.to), (at(0), at(50)));
+        assert_eq!(merged.session_id, Some(1));
+        assert!(moved.is_empty());
+    }
+
+    #[test]
+    fn merge_moves_the_pause_after() {
+        let pause = record(3, 20, 30, RecordKind::Pause);
+        let (merged, moved) = merge_records(&[record(1, 0, 20, RecordKind::Work),
+                                              record(2, 30, 50, RecordKind::Work)],
+                                            &[pause]).unwrap();
+
+        assert_eq!((*merged.from, *merged.to), (at(0), at(40)));
+        assert_eq!(moved.len(), 1);
+        assert_eq!((moved[0].id, *moved[0].from, *moved[0].to), (3, at(40), at(50)));
+    }
+
+    #[test]
+    fn merge_overlapping_counts_shared_time_once() {
+        let (merged, _) = merge_records(&[record(1, 0, 30, RecordKind::Work),
+                                          record(2, 20, 50, RecordKind::Work),
+                                          record(3, 5, 10, RecordKind::Work)], &[]).unwrap();
+
+        assert_eq!((*merged.from, *merged.to), (at(0), at(50)));
+    }
+
+    #[test]
+    fn merge_refuses_different_kinds_and_tasks() {
+        assert!(merge_records(&[record(1, 0, 25, RecordKind::Work),
+                                record(2, 25, 35, RecordKind::Break)], &[]).is_none());
+
+        let other = TimeRecord{uid: "b".to_string(), ..record(2, 25, 50, RecordKind::Work)};
+        assert!(merge_records(&[record(1, 0, 25, RecordKind::Work), other], &[]).is_none());
+
+        assert!(merge_records(&[], &[]).is_none());
+    }
+
+    #[test]
+    fn split_inside() {
+        let (first, second) = split_record(&record(1, 0, 50, RecordKind::Work), &at(20), "b")
+            .unwrap();
+
+        assert_eq!((first.id, *first.from, *first.to, first.uid.as_str()),
+                   (1, at(0), at(20), "a"));
+        assert_eq!((second.id, *second.from, *second.to, second.uid.as_str()),
+                   (0, at(20), at(50), "b"));
+        assert_eq!(second.session_id, Some(1));
+    }
+
+    #[test]
+    fn split_at_boundary_is_refused() {
+        let r = record(1, 0, 50, RecordKind::Work);
+
+        assert!(split_record(&r, &at(0), "a").is_none());
+        assert!(split_record(&r, &at(50), "a").is_none());
+        assert!(split_record(&r, &at(-5), "a").is_none());
+        assert!(split_record(&r, &at(55), "a").is_none());
+    }
+}
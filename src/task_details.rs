@@ -4,7 +4,7 @@ use chrono::prelude::*;
 
 use druid::im::{Vector, OrdSet};
 use druid::lens::{self, LensExt};
-use druid::widget::{Button, Either, CrossAxisAlignment, Flex, Split, Label, List, Scroll, Controller, Painter, Radio, SizedBox};
+use druid::widget::{Button, Checkbox, Either, CrossAxisAlignment, Flex, Split, Label, List, Scroll, Controller, Painter, Radio, SizedBox, TextBox};
 
 use druid::{Color, Cursor, LinearGradient,
     Data, PaintCtx, RenderContext, Env, Event, EventCtx, LifeCycle, LifeCycleCtx,
@@ -21,9 +21,10 @@ const COLORS_COUNT: usize = 6;
 const GRADIENT_COLORS: [Color; COLORS_COUNT] =
     [Color::RED, Color::YELLOW, Color::GREEN, Color::BLUE, Color::NAVY, Color::PURPLE];
 
-type TaskSummaryCtx = (Task, TaskViewState, TimePrefixSum);
+/// task, view state, time sum and number of completed pomodoros
+type TaskSummaryCtx = (Task, TaskViewState, TimePrefixSum, u32);
 
-fn get_day_time(((_task, _view_state, sum), i): &(TaskSummaryCtx, i64))-> String {
+fn get_day_time(((_task, _view_state, sum, _), i): &(TaskSummaryCtx, i64))-> String {
 
     let now = time::daystart(Local::now());
 
@@ -51,37 +52,41 @@ pub fn task_summary_widget() -> impl Widget<(TaskSummaryCtx, bool)> {
         List::new(||
                   Label::new(|ctx: &(TaskSummaryCtx, i64), _env: &_| {
                       get_day_time(ctx)
-                  }).with_font(FONT_LOG_DESCR.clone()))
+                  }).with_font(FONT_LOG_DESCR))
         .padding((10.0, 10.0, 25.0, 10.0))
         .lens(lens::Identity.map(
-            |tvs: &TaskSummaryCtx| ((tvs.0.clone(), tvs.1.clone(), tvs.2.clone()), (0..28).collect()),
+            |tvs: &TaskSummaryCtx| (tvs.clone(), (0..28).collect()),
             |_: &mut TaskSummaryCtx, _:(TaskSummaryCtx,Vector<i64>)| {}));
 
 
     let split = Split::columns(
         Flex::column()
-            .with_child(Label::new("Total time").with_font(FONT_CAPTION_DESCR.clone()))
+            .with_child(Label::new("Total time").with_font(FONT_CAPTION_DESCR))
             .with_default_spacer()
             .with_child(widgets::duration_widget()
                         .lens(lens::Map::new(
-                            |(_task, _vs, sum): &TaskSummaryCtx|
+                            |(_task, _vs, sum, _): &TaskSummaryCtx|
                             Rc::new(time::get_duration(sum, &Local::now())),
                             |_, _| {})))
+            .with_default_spacer()
+            .with_child(Label::new(|(_, _, _, completed): &TaskSummaryCtx, _env: &_|
+                                   format!("Pomodoros completed: {}", completed))
+                        .with_font(FONT_LOG_DESCR))
             ,
         Flex::column()
-            .with_child(Label::new("Retrospective").with_font(FONT_CAPTION_DESCR.clone()))
+            .with_child(Label::new("Retrospective").with_font(FONT_CAPTION_DESCR))
             .with_default_spacer()
             .with_flex_child(
                 Scroll::new(Flex::row().with_child(days_list))
                     .vertical()
                     .background(
-                        Painter::new(|ctx: &mut PaintCtx, _item: &_, _env| {
+                        Painter::new(|ctx: &mut PaintCtx, _item: &_, env| {
                             let bounds = ctx.size().to_rect();
-                            ctx.stroke(bounds, &TASK_COLOR_BG, 2.0);
+                            ctx.stroke(bounds, &env.get(TASK_COLOR_BG), 2.0);
                         })), 1.0)
     ).bar_size(0.0);
 
-    Either::new(|((_, _, _), visible): &(TaskSummaryCtx, bool), _env: &Env|
+    Either::new(|((_, _, _, _), visible): &(TaskSummaryCtx, bool), _env: &Env|
                 *visible == true,
                 split
                 .lens(druid::lens!((TaskSummaryCtx, bool), 0)),
@@ -93,7 +98,7 @@ pub fn task_edit_widget() -> impl Widget<(Task, bool)> {
 
     column.add_child(
         Flex::row()
-            .with_child(Label::new("Name").with_font(FONT_CAPTION_DESCR.clone()))
+            .with_child(Label::new("Name").with_font(FONT_CAPTION_DESCR))
             .with_default_spacer()
             .with_child(
                 EditableLabel::parse()
@@ -112,7 +117,7 @@ pub fn task_edit_widget() -> impl Widget<(Task, bool)> {
 
     column.add_child(
         Flex::row()
-            .with_child(Label::new("Status") .with_font(FONT_CAPTION_DESCR.clone()))
+            .with_child(Label::new("Status") .with_font(FONT_CAPTION_DESCR))
             .with_default_spacer()
             .with_child(Radio::new("needs action" , TaskStatus::NeedsAction))
             .with_child(Radio::new("in process"   , TaskStatus::InProcess))
@@ -126,7 +131,7 @@ pub fn task_edit_widget() -> impl Widget<(Task, bool)> {
 
     column.add_child(
         Flex::row()
-            .with_child(Label::new("Priority") .with_font(FONT_CAPTION_DESCR.clone()))
+            .with_child(Label::new("Priority") .with_font(FONT_CAPTION_DESCR))
             .with_default_spacer()
             .with_child(Radio::new("low"         , CuaPriority::Low))
             .with_child(Radio::new("normal"      , CuaPriority::Normal))
@@ -156,9 +161,9 @@ pub fn task_edit_widget() -> impl Widget<(Task, bool)> {
                         .align_horizontal(UnitPoint::LEFT)
                         .padding(10.0))
                 .background(
-                    Painter::new(|ctx: &mut PaintCtx, _item: &_, _env| {
+                    Painter::new(|ctx: &mut PaintCtx, _item: &_, env| {
                         let bounds = ctx.size().to_rect();
-                        ctx.stroke(bounds, &TASK_COLOR_BG, 2.0);
+                        ctx.stroke(bounds, &env.get(TASK_COLOR_BG), 2.0);
                     }))
 
         })
@@ -177,7 +182,7 @@ pub fn task_edit_widget() -> impl Widget<(Task, bool)> {
 
     column.add_child(
         Flex::row()
-            .with_child(Label::new("Tags").with_font(FONT_CAPTION_DESCR.clone()))
+            .with_child(Label::new("Tags").with_font(FONT_CAPTION_DESCR))
             .with_spacer(20.0)
             .with_child(new_tag_edit.padding(10.0))
             .with_default_spacer()
@@ -188,7 +193,7 @@ pub fn task_edit_widget() -> impl Widget<(Task, bool)> {
 
     column.add_spacer(15.0);
 
-    column.add_child(Label::new("Description").with_font(FONT_CAPTION_DESCR.clone()));
+    column.add_child(Label::new("Description").with_font(FONT_CAPTION_DESCR));
     column.add_default_spacer();
     column.add_child(
         EditableLabel::parse()
@@ -204,7 +209,7 @@ pub fn task_edit_widget() -> impl Widget<(Task, bool)> {
 
     column.add_child(
         Flex::row()
-            .with_child(Label::new("Work/rest duration").with_font(FONT_CAPTION_DESCR.clone()))
+            .with_child(Label::new("Work/rest duration").with_font(FONT_CAPTION_DESCR))
             .with_default_spacer()
             .with_child(
                 EditableLabel::parse()
@@ -230,14 +235,28 @@ pub fn task_edit_widget() -> impl Widget<(Task, bool)> {
                         },
                     )).fix_width(40.0))
             .with_default_spacer()
-            .with_child(Label::new("min").with_font(FONT_CAPTION_DESCR.clone()))
+            .with_child(Label::new("min").with_font(FONT_CAPTION_DESCR))
+            .with_default_spacer()
+            .with_child(Checkbox::new("stopwatch, no timer").lens(Task::stopwatch))
+    );
+
+    column.add_spacer(15.0);
+
+    column.add_child(
+        Flex::row()
+            .with_child(Label::new("Sound").with_font(FONT_CAPTION_DESCR))
+            .with_default_spacer()
+            .with_flex_child(TextBox::new()
+                             .with_placeholder("default, or notif, bell, none or a file")
+                             .expand_width()
+                             .lens(Task::sound), 1.0)
     );
 
     column.add_spacer(15.0);
 
     column.add_child(
         Flex::row()
-        .with_child(Label::new("Color").with_font(FONT_CAPTION_DESCR.clone()))
+        .with_child(Label::new("Color").with_font(FONT_CAPTION_DESCR))
         .with_default_spacer()
         .with_flex_child(
             SizedBox::new(Painter::new(|ctx, _data: &_, _env| {
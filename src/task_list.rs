@@ -1,4 +1,5 @@
 use std::rc::Rc;
+use std::path::Path;
 
 use druid::im::{Vector};
 use druid::widget::prelude::*;
@@ -7,10 +8,9 @@ use druid::widget::{Flex, Label, List, Controller, ControllerHost, Container, Pa
 
 use druid::kurbo::Circle;
 
-use druid::{PaintCtx, RenderContext, Env, Event, EventCtx, Point,
+use druid::{commands, PaintCtx, RenderContext, Env, Event, EventCtx, Point,
             Menu, MenuItem, TimerToken, LocalizedString, UnitPoint, Widget, WidgetPod, WidgetExt,};
 
-use notify_rust::Notification;
 
 use chrono::prelude::*;
 
@@ -18,11 +18,26 @@ use crate::task::*;
 use crate::app_model::*;
 use crate::common::*;
 use crate::db;
-use crate::utils;
+use crate::config;
+use crate::idle;
+use crate::sound::{Ambient, Sound};
+use crate::notification::{self, Action};
+use crate::time_entry::*;
+use crate::settings::*;
+use crate::break_overlay::*;
+use crate::mini_window::*;
+use crate::ipc;
 
 pub struct TaskListWidget {
     inner: WidgetPod<(AppModel, Vector<String>),
-                  Scroll<(AppModel, Vector<String>), List<(AppModel, String)>>>
+                  Scroll<(AppModel, Vector<String>), List<(AppModel, String)>>>,
+    checkpoint_timer: TimerToken,
+    /// work record the user walked away from, `to` is not known yet
+    idle_record: Option<TimeRecord>,
+    tick_timer: TimerToken,
+    last_tick: DateTime<Utc>,
+    /// time nothing was tracked since, or of the last reminder about it
+    untracked_since: DateTime<Utc>,
 }
 
 impl TaskListWidget {
@@ -31,32 +46,36 @@ impl TaskListWidget {
         let inner = Scroll::new(List::new(|| {
 
             let task_painter =
-                Painter::new(|ctx: &mut PaintCtx, (shared, uid): &(AppModel, String), _env| {
+                Painter::new(|ctx: &mut PaintCtx, (shared, uid): &(AppModel, String), env| {
                     let bounds = ctx.size().to_rect();
 
                     if let Some(ref selected) = shared.selected_task {
                         if selected.contains(uid) {
-                            ctx.fill(bounds, &TASK_COLOR_BG);
+                            ctx.fill(bounds, &env.get(TASK_COLOR_BG));
                         }
                     }
 
                     match shared.tracking.state {
                         TrackingState::Active(ref active) if uid.eq(active) => {
-                            ctx.stroke(bounds, &TASK_ACTIVE_COLOR_BG, 4.0);
+                            if get_overtime(shared).is_some() {
+                                ctx.stroke(bounds, &env.get(TASK_OVERTIME_COLOR_BG), 4.0);
+                            } else {
+                                ctx.stroke(bounds, &env.get(TASK_ACTIVE_COLOR_BG), 4.0);
+                            }
                             return;
                         },
                         TrackingState::Paused(ref paused) if uid.eq(paused) => {
-                            ctx.stroke(bounds, &TASK_PAUSE_COLOR_BG, 4.0);
+                            ctx.stroke(bounds, &env.get(TASK_PAUSE_COLOR_BG), 4.0);
                             return;
                         },
                         TrackingState::Break(ref rest) if uid.eq(rest) => {
-                            ctx.stroke(bounds, &TASK_REST_COLOR_BG, 4.0);
+                            ctx.stroke(bounds, &env.get(TASK_REST_COLOR_BG), 4.0);
                             return;
                         },
                         _ => (),
                     };
 
-                    ctx.stroke(bounds, &TASK_COLOR_BG, 2.0);
+                    ctx.stroke(bounds, &env.get(TASK_COLOR_BG), 2.0);
                 });
 
             let container = Container::new(
@@ -98,7 +117,100 @@ impl TaskListWidget {
         })
         .with_spacing(10.)).vertical();
 
-        return TaskListWidget{inner: WidgetPod::new(inner)};
+        return TaskListWidget{inner: WidgetPod::new(inner), checkpoint_timer: TimerToken::INVALID,
+                              idle_record: None, tick_timer: TimerToken::INVALID,
+                              last_tick: Utc::now(), untracked_since: Utc::now()};
+    }
+
+    /// Compare the wall clock with the last UI tick. A jump forward means
+    /// the computer was suspended or the clock was changed: the session is
+    /// cut where the time stopped and the user decides about the gap.
+    fn check_clock_jump(&mut self, data: &mut AppModel) {
+        let now = Utc::now();
+        let last_tick = std::mem::replace(&mut self.last_tick, now);
+        let gap = now.signed_duration_since(last_tick);
+
+        if gap < chrono::Duration::zero() {
+            println!("clock moved back by {}s", -gap.num_seconds());
+            return;
+        }
+
+        if gap.to_std().unwrap() <= UI_TIMER_INTERVAL + CLOCK_JUMP_THRESHOLD {
+            return;
+        }
+
+        println!("clock jumped by {}s", gap.num_seconds());
+
+        // the session could have been started right before the jump
+        let at = std::cmp::max(last_tick, *data.tracking.timestamp);
+
+        let (uid, kind) = match data.tracking.state.clone() {
+            TrackingState::Active(uid) => {
+                pause_tracking_at(data, uid.clone(), at);
+                (uid, RecordKind::Work)
+            },
+            TrackingState::Break(uid) => {
+                stop_session_at(data, TrackingState::Inactive, SessionOutcome::Stopped, at);
+                (uid, RecordKind::Break)
+            },
+            _ => return,
+        };
+
+        data.prompts.push_back(Prompt::ClockJump(TimeRecord{
+            id: data.tracking.record_id.unwrap_or_default(),
+            from: Rc::new(at), to: Rc::new(now), uid, kind,
+            session_id: data.tracking.session_id}));
+    }
+
+    /// Remind to track after `reminder_minutes` without a session during
+    /// working hours, with a button to resume the last task.
+    fn check_untracked(&mut self, data: &AppModel) {
+        if data.config.reminder_minutes <= 0 {
+            return;
+        }
+
+        if !matches!(data.tracking.state, TrackingState::Inactive) {
+            return;
+        }
+
+        let now = Utc::now();
+
+        if now - self.untracked_since < chrono::Duration::minutes(data.config.reminder_minutes) {
+            return;
+        }
+
+        self.untracked_since = now;
+
+        if !data.config.is_working_time(DateTime::from(now)) {
+            return;
+        }
+
+        let (summary, actions) = match get_last_task(&data.tasks, &data.records) {
+            Some(uid) => (format!("netupi: not tracking anything, resume \"{}\"?",
+                                  data.tasks.get(&uid).unwrap().name),
+                          vec![Action::task("Resume last task", COMMAND_TASK_START, uid)]),
+            None => ("netupi: not tracking anything".to_string(), vec![]),
+        };
+
+        notify(data, &summary, actions);
+    }
+
+    /// Note when the user went idle during a work session and ask about
+    /// that time once they are back.
+    fn check_idle(&mut self, data: &mut AppModel) {
+        let source = match data.idle {
+            Some(ref source) if data.config.idle_minutes > 0 => source.clone(),
+            _ => return,
+        };
+
+        let threshold = chrono::Duration::minutes(data.config.idle_minutes);
+
+        match idle::check_idle(&mut self.idle_record, &*source, threshold, &data.tracking,
+                               Utc::now()) {
+            Ok(Some(record)) => data.prompts.push_back(Prompt::Idle(record)),
+            Ok(None) => {},
+            Err(what) => println!("idle error: {}", what),
+        }
     }
 }
 
@@ -111,10 +223,25 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
             // https://github.com/rust-lang/rust/issues/51114
 
             Event::Command(cmd) if cmd.is(COMMAND_TASK_START) => {
-                stop_tracking(&mut data.0, TrackingState::Inactive);
-                start_tracking(&mut data.0, cmd.get(COMMAND_TASK_START).unwrap().clone(), ctx);
+                let uid = cmd.get(COMMAND_TASK_START).unwrap().clone();
+                let outcome = match data.0.tracking.state.uid() {
+                    Some(cur) if !cur.eq(&uid) => SessionOutcome::Switched,
+                    _ => SessionOutcome::Stopped,
+                };
+
+                stop_session(&mut data.0, TrackingState::Inactive, outcome);
+                start_tracking(&mut data.0, uid, ctx);
             },
 
+            Event::Command(cmd) if cmd.is(COMMAND_TRACKING_RESTORE) => {
+                rearm_tracking(&mut data.0, ctx);
+                start_ambient(&data.0);
+
+                if let TrackingState::Break(_) = data.0.tracking.state {
+                    open_break_overlay(&mut data.0, ctx);
+                }
+            }
+
             Event::Command(cmd) if cmd.is(COMMAND_TASK_STOP) => {
                 stop_tracking(&mut data.0, TrackingState::Inactive);
             }
@@ -122,17 +249,28 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
             Event::Command(cmd) if cmd.is(COMMAND_TASK_PAUSE) => {
                 let uid = match &data.0.tracking.state {
                     TrackingState::Active(uid) => uid.clone(),
-                    _ => panic!("state is not active"),
+                    // sent from outside, the session ended in the meantime
+                    _ => return,
                 };
                 pause_tracking(&mut data.0, uid);
             }
 
            Event::Command(cmd) if cmd.is(COMMAND_TASK_RESUME) => {
-               resume_tracking(&mut data.0, cmd.get(COMMAND_TASK_RESUME).unwrap().clone(), ctx);
+               let uid = cmd.get(COMMAND_TASK_RESUME).unwrap().clone();
+
+               match &data.0.tracking.state {
+                   TrackingState::Paused(paused) if *paused == uid => {},
+                   // sent from outside, the session went on in the meantime
+                   _ => return,
+               }
+
+               resume_tracking(&mut data.0, uid, ctx);
             }
 
             Event::Command(cmd) if cmd.is(COMMAND_TASK_NEW) => {
-                let task = Task::new_simple("new task".to_string());
+                let mut task = Task::new_simple("new task".to_string());
+                task.work_duration = Rc::new(chrono::Duration::minutes(data.0.config.default_work_minutes));
+                task.break_duration = Rc::new(chrono::Duration::minutes(data.0.config.default_break_minutes));
                 let uid = task.uid.clone();
 
                 if let Err(what) = db::add_task(data.0.db.clone(), &task) {
@@ -148,6 +286,91 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
                 ctx.set_focus(TASK_EDIT_WIDGET);
                 ctx.request_update();
             },
+            Event::Command(cmd) if cmd.is(COMMAND_TASK_BREAK) => {
+                let uid = cmd.get(COMMAND_TASK_BREAK).unwrap().clone();
+
+                // a notification button may be clicked late
+                if data.0.tracking.state.uid() == Some(&uid) && get_overtime(&data.0).is_some() {
+                    stop_session(&mut data.0, TrackingState::Inactive, SessionOutcome::Completed);
+                    start_rest(&mut data.0, uid, ctx);
+                }
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_TASK_EXTEND) => {
+                extend_tracking(&mut data.0, ctx);
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_TASK_BACKDATE) => {
+                backdate_tracking(&mut data.0, ctx);
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_TASK_FINISH) => {
+                match data.0.tracking.state.clone() {
+                    TrackingState::Active(uid) | TrackingState::Paused(uid)
+                        if !is_stopwatch(&data.0, &uid) => finish_pomodoro(&mut data.0, uid, ctx),
+                    _ => {},
+                }
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_BREAK_START) => {
+                // the prompt or the notification may be answered late, after
+                // tracking went on
+                if let TrackingState::Inactive = data.0.tracking.state {
+                    drop_break_prompts(&mut data.0);
+                    start_rest(&mut data.0, cmd.get(COMMAND_BREAK_START).unwrap().clone(), ctx);
+                }
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_BREAK_SKIP) => {
+                let uid = cmd.get(COMMAND_BREAK_SKIP).unwrap().clone();
+
+                match data.0.tracking.state.clone() {
+                    TrackingState::Inactive => {
+                        drop_break_prompts(&mut data.0);
+                        skip_rest(&mut data.0, uid, ctx);
+                    },
+                    TrackingState::Break(ref current) if *current == uid => {
+                        stop_tracking(&mut data.0, TrackingState::Inactive);
+                        skip_rest(&mut data.0, uid, ctx);
+                    },
+                    _ => {},
+                }
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_CLOCK_JUMP_WORK) => {
+                count_gap_as_work(&mut data.0, cmd.get(COMMAND_CLOCK_JUMP_WORK).unwrap(), ctx);
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_IDLE_DISCARD) => {
+                cut_idle_time(&mut data.0, cmd.get(COMMAND_IDLE_DISCARD).unwrap(), ctx);
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_IDLE_ASSIGN) => {
+                let (idle, uid) = cmd.get(COMMAND_IDLE_ASSIGN).unwrap().clone();
+
+                if let Some((from, to)) = cut_idle_time(&mut data.0, &idle, ctx) {
+                    add_idle_record(&mut data.0, TimeRecord{id: 0, from, to, uid,
+                                                            kind: RecordKind::Work,
+                                                            session_id: None});
+                }
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_TIME_ENTRY_NEW) => {
+                data.0.time_entry = new_time_entry_draft(&data.0);
+                ctx.new_window(time_entry_window());
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_SOUND_MUTE) => {
+                toggle_mute(&mut data.0);
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_SETTINGS_OPEN) => {
+                data.0.settings = data.0.config.clone();
+                ctx.new_window(settings_window());
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_IPC_SNAPSHOT) => {
+                // the client may have given up waiting
+                let _ = cmd.get(COMMAND_IPC_SNAPSHOT).unwrap().send(ipc::snapshot(&data.0));
+            },
+            Event::Command(cmd) if cmd.is(COMMAND_MINI_WINDOW) => {
+                match data.0.mini_window.take() {
+                    Some(id) => ctx.submit_command(commands::CLOSE_WINDOW.to(*id)),
+                    None => {
+                        let window = mini_window();
+                        data.0.mini_window = Some(Rc::new(window.id));
+                        ctx.new_window(window);
+                    },
+                }
+            },
             Event::Command(cmd) if cmd.is(COMMAND_TASK_COMPLETED) => {
                 let uid = cmd.get(COMMAND_TASK_COMPLETED).unwrap().clone();
                 let mut task = data.0.tasks.get(&uid).expect("unknown uid").clone();
@@ -161,9 +384,9 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
                     TrackingState::Active(cur) if cur.eq(&uid)
                         => stop_tracking(&mut data.0, TrackingState::Inactive),
                     TrackingState::Paused(cur) if cur.eq(&uid)
-                        => data.0.tracking.state = TrackingState::Inactive,
+                        => stop_tracking(&mut data.0, TrackingState::Inactive),
                     TrackingState::Break(cur) if cur.eq(&uid)
-                        => data.0.tracking.state = TrackingState::Inactive,
+                        => stop_tracking(&mut data.0, TrackingState::Inactive),
                     _ => (),
                 };
 
@@ -177,31 +400,60 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
             Event::Command(cmd) if cmd.is(COMMAND_TLIST_REQUEST_FOCUS) => {
                 ctx.request_focus();
             }
+            Event::Timer(id) if *id == self.tick_timer => {
+                self.tick_timer = ctx.request_timer(UI_TIMER_INTERVAL);
+                self.check_clock_jump(&mut data.0);
+            },
+            Event::Timer(id) if *id == self.checkpoint_timer => {
+                self.checkpoint_timer = ctx.request_timer(CHECKPOINT_INTERVAL);
+                self.check_clock_jump(&mut data.0);
+                checkpoint_tracking(&data.0);
+                self.check_idle(&mut data.0);
+                self.check_untracked(&data.0);
+            },
             Event::Timer(id) => {
-                if *id == *data.0.tracking.timer_id {
-                    utils::play_sound(SOUND_TASK_FINISH, WORK_TIMER_VOLUME);
+                // a timer due during a suspend fires late, cut the session first
+                self.check_clock_jump(&mut data.0);
 
+                if *id == *data.0.tracking.timer_id {
                     match data.0.tracking.state.clone() {
+                        // flow mode: only tell, the pomodoro goes on as overtime
+                        TrackingState::Active(uid) if data.0.config.flow_mode => {
+                            data.0.tracking.timer_id = Rc::new(TimerToken::INVALID);
+
+                            play_event_sound(&data.0, &work_finished_sound(&data.0, &uid));
+                            notify(&data.0, &format!("netupi: \"{}\" session finished, overtime started",
+                                                     data.0.tasks.get(&uid).unwrap().name),
+                                   vec![Action::task("Start break", COMMAND_TASK_BREAK, uid.clone()),
+                                        Action::session("+5 min", COMMAND_TASK_EXTEND, uid.clone())]);
+                        },
                         TrackingState::Active(uid) => {
-                            stop_tracking(&mut data.0, TrackingState::Inactive);
+                            play_event_sound(&data.0, &work_finished_sound(&data.0, &uid));
+                            finish_pomodoro(&mut data.0, uid.clone(), ctx);
 
-                            #[cfg(not(target_os = "windows"))]
-                            Notification::new()
-                                .summary(&format!("netupi: \"{}\" session finished",
-                                               data.0.tasks.get(&uid).unwrap().name))
-                                .show();
-
-                            start_rest(&mut data.0, uid, ctx);
+                            notify(&data.0, &format!("netupi: \"{}\" session finished",
+                                                     data.0.tasks.get(&uid).unwrap().name),
+                                   work_finished_actions(&data.0, &uid));
                         },
                         TrackingState::Break(uid) => {
+                            play_event_sound(&data.0, &data.0.config.sound_break_finished);
+
+                            stop_tracking(&mut data.0, TrackingState::Inactive);
 
-                            #[cfg(not(target_os = "windows"))]
-                            Notification::new()
-                                .summary(&format!("netupi: \"{}\" break finished",
-                                               data.0.tasks.get(&uid).unwrap().name))
-                                .show();
+                            if can_auto_start(&data.0) {
+                                start_next(&mut data.0, uid.clone(), ctx);
+                            }
 
-                            data.0.tracking.state = TrackingState::Inactive
+                            let actions = match data.0.tracking.state {
+                                TrackingState::Inactive =>
+                                    vec![Action::task("Start next pomodoro", COMMAND_TASK_START,
+                                                      uid.clone())],
+                                _ => vec![],
+                            };
+
+                            notify(&data.0, &format!("netupi: \"{}\" break finished",
+                                                     data.0.tasks.get(&uid).unwrap().name),
+                                   actions);
                         },
                         _ => {},
                     };
@@ -256,31 +508,59 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
                         pause_tracking(&mut data.0, uid),
 
                     TrackingState::Active(_) => {
-                        stop_tracking(&mut data.0, TrackingState::Inactive);
+                        stop_session(&mut data.0, TrackingState::Inactive,
+                                     SessionOutcome::Switched);
                         start_tracking(&mut data.0, selected, ctx);
                     },
                     TrackingState::Paused(uid) if uid.eq(&selected) =>
                         resume_tracking(&mut data.0, uid, ctx),
 
                     TrackingState::Paused(_uid) => {
-                        stop_tracking(&mut data.0, TrackingState::Inactive);
+                        stop_session(&mut data.0, TrackingState::Inactive,
+                                     SessionOutcome::Switched);
                         start_tracking(&mut data.0, selected, ctx);
                     },
-                    TrackingState::Break(uid) => start_tracking(&mut data.0, uid, ctx),
+                    TrackingState::Break(uid) => {
+                        stop_tracking(&mut data.0, TrackingState::Inactive);
+                        start_tracking(&mut data.0, uid, ctx);
+                    },
                 }
             }
 
             Event::KeyUp(key) if key.code == druid::Code::Escape => {
-                match data.0.tracking.state.clone() {
-                    TrackingState::Active(_) => stop_tracking(&mut data.0, TrackingState::Inactive),
-                    _ => data.0.tracking.state = TrackingState::Inactive,
-                }
+                stop_tracking(&mut data.0, TrackingState::Inactive);
             },
 
             Event::KeyUp(key) if key.code == druid::Code::KeyN => {
                 ctx.submit_command(COMMAND_TASK_NEW.with(()));
             },
 
+            Event::KeyUp(key) if key.code == druid::Code::KeyB => {
+                if let Some(uid) = data.0.tracking.state.uid() {
+                    ctx.submit_command(COMMAND_TASK_BREAK.with(uid.clone()));
+                }
+            },
+
+            Event::KeyUp(key) if key.code == druid::Code::KeyM => {
+                ctx.submit_command(COMMAND_SOUND_MUTE.with(()));
+            },
+
+            Event::KeyUp(key) if key.code == druid::Code::KeyX => {
+                ctx.submit_command(COMMAND_TASK_EXTEND.with(()));
+            },
+
+            Event::KeyUp(key) if key.code == druid::Code::KeyD => {
+                ctx.submit_command(COMMAND_TASK_BACKDATE.with(()));
+            },
+
+            Event::KeyUp(key) if key.code == druid::Code::KeyF => {
+                ctx.submit_command(COMMAND_TASK_FINISH.with(()));
+            },
+
+            Event::KeyUp(key) if key.code == druid::Code::KeyT => {
+                ctx.submit_command(COMMAND_TIME_ENTRY_NEW.with(()));
+            },
+
             Event::KeyUp(key) if key.code == druid::Code::KeyE => {
                 if !data.0.show_task_edit {
                     data.0.show_task_edit = true;
@@ -310,6 +590,12 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &(AppModel, Vector<String>), _env: &Env) {
         match event {
+            LifeCycle::WidgetAdded => {
+                self.checkpoint_timer = ctx.request_timer(CHECKPOINT_INTERVAL);
+                self.tick_timer = ctx.request_timer(UI_TIMER_INTERVAL);
+                ctx.submit_command(COMMAND_TRACKING_RESTORE.with(()));
+                self.inner.lifecycle(ctx, event, _data, _env)
+            },
             LifeCycle::BuildFocusChain => {
                 ctx.register_for_focus();
                 ctx.submit_command(COMMAND_TLIST_REQUEST_FOCUS.with(()));
@@ -323,6 +609,13 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
     }
 
     fn update(&mut self, _ctx: &mut UpdateCtx, _old_data: &(AppModel, Vector<String>), _data: &(AppModel, Vector<String>), _env: &Env) {
+        update_ambient(&_old_data.0, &_data.0);
+
+        if !matches!(_old_data.0.tracking.state, TrackingState::Inactive) &&
+            matches!(_data.0.tracking.state, TrackingState::Inactive) {
+            self.untracked_since = Utc::now();
+        }
+
         self.inner.update(_ctx, _data, _env)
     }
 
@@ -342,7 +635,7 @@ impl Widget<(AppModel, Vector<String>)> for TaskListWidget {
         self.inner.paint(ctx, data, env);
         let bounds = ctx.size().to_rect();
         if ctx.has_focus() {
-            ctx.stroke(bounds, &TASK_FOCUS_BORDER, 2.0);
+            ctx.stroke(bounds, &env.get(TASK_FOCUS_BORDER), 2.0);
         }
     }
 }
@@ -376,8 +669,14 @@ pub fn make_task_menu(d: &AppModel, current_opt: &Option<String>) -> Menu<AppMod
                     ctx.submit_command(COMMAND_TASK_NEW.with(()));
                     });
 
+    let time_entry_entry = MenuItem::new(LocalizedString::new("Add time entry..."))
+                .on_activate(
+                    move |ctx, _: &mut AppModel, _env| {
+                    ctx.submit_command(COMMAND_TIME_ENTRY_NEW.with(()));
+                    });
+
     if current_opt.is_none() {
-        return result.entry(new_task_entry);
+        return result.entry(new_task_entry).entry(time_entry_entry);
     }
 
     let current = current_opt.as_ref().unwrap();
@@ -409,13 +708,56 @@ pub fn make_task_menu(d: &AppModel, current_opt: &Option<String>) -> Menu<AppMod
             })
     };
 
+    let break_entry = {
+        let uid_for_closure = current.clone();
+        MenuItem::new(LocalizedString::new("Start break")).on_activate(
+            move |ctx, _d: &mut AppModel, _env| {
+                ctx.submit_command(COMMAND_TASK_BREAK.with(uid_for_closure.clone()));
+            })
+    };
+
+    // x, d and f are handled by the task list, the labels only name them
+    let extend_entry = MenuItem::new(LocalizedString::new("+5 min (x)")).on_activate(
+        move |ctx, _d: &mut AppModel, _env| {
+            ctx.submit_command(COMMAND_TASK_EXTEND.with(()));
+        });
+
+    let backdate_entry = MenuItem::new(LocalizedString::new("Started 10 min earlier (d)")).on_activate(
+        move |ctx, _d: &mut AppModel, _env| {
+            ctx.submit_command(COMMAND_TASK_BACKDATE.with(()));
+        });
+
+    let finish_entry = MenuItem::new(LocalizedString::new("Finish now as completed (f)")).on_activate(
+        move |ctx, _d: &mut AppModel, _env| {
+            ctx.submit_command(COMMAND_TASK_FINISH.with(()));
+        });
+
+    let overtime = get_overtime(d).is_some();
+    let stopwatch = is_stopwatch(d, current);
+
     match &d.tracking.state {
+        TrackingState::Active(uid) if current.eq(uid) && stopwatch =>
+            result = result.entry(pause_entry).entry(backdate_entry).entry(stop_entry),
+
+        TrackingState::Active(uid) if current.eq(uid) && overtime =>
+            result = result.entry(pause_entry).entry(break_entry).entry(extend_entry)
+                .entry(backdate_entry).entry(stop_entry),
+
         TrackingState::Active(uid) if current.eq(uid) =>
-            result = result.entry(pause_entry).entry(stop_entry),
+            result = result.entry(pause_entry).entry(extend_entry).entry(backdate_entry)
+                .entry(finish_entry).entry(stop_entry),
 
-        TrackingState::Paused(uid) if current.eq(uid) =>
+        TrackingState::Paused(uid) if current.eq(uid) && stopwatch =>
             result = result.entry(resume_entry).entry(stop_entry),
 
+        TrackingState::Paused(uid) if current.eq(uid) && overtime =>
+            result = result.entry(resume_entry).entry(break_entry).entry(extend_entry)
+                .entry(stop_entry),
+
+        TrackingState::Paused(uid) if current.eq(uid) =>
+            result = result.entry(resume_entry).entry(extend_entry).entry(finish_entry)
+                .entry(stop_entry),
+
         TrackingState::Break(uid) if current.eq(uid) =>
             result = result.entry(start_entry),
 
@@ -441,6 +783,9 @@ pub fn make_task_menu(d: &AppModel, current_opt: &Option<String>) -> Menu<AppMod
         .entry(
             new_task_entry,
         )
+        .entry(
+            time_entry_entry,
+        )
         .entry(
             MenuItem::new(LocalizedString::new("Archive")).on_activate(
                 move |ctx, _: &mut AppModel, _env| {
@@ -452,17 +797,285 @@ pub fn make_task_menu(d: &AppModel, current_opt: &Option<String>) -> Menu<AppMod
 
 fn start_rest(data: &mut AppModel, uid: String, ctx: &mut EventCtx) {
     data.tracking.timestamp = Rc::new(Utc::now());
+    open_record(data, &uid, RecordKind::Break);
     data.tracking.timer_id =
         Rc::new(ctx.request_timer(get_rest_interval(data, &uid).to_std().unwrap()));
     data.tracking.state = TrackingState::Break(uid);
+    save_tracking(data);
+    open_break_overlay(data, ctx);
+}
+
+/// Cover the screen for the break if the settings ask for it.
+fn open_break_overlay(data: &mut AppModel, ctx: &mut EventCtx) {
+    if data.config.break_overlay {
+        data.break_skip_text.clear();
+        ctx.new_window(break_overlay_window());
+    }
+}
+
+/// Play the sound `setting` names, unless sounds are muted.
+fn play_event_sound(data: &AppModel, setting: &str) {
+    if !data.config.sound {
+        return;
+    }
+
+    if let Some(sound) = Sound::from_setting(setting, config_dir(data)) {
+        data.audio.play(sound, data.config.volume as f32);
+    }
+}
+
+/// Sound files are looked up next to the config file.
+fn config_dir(data: &AppModel) -> &Path {
+    data.config_path.parent().unwrap_or(Path::new(""))
+}
+
+/// Ambient sound setting and volume for the state of `data`, `None` when
+/// nothing should play.
+fn ambient_wanted(data: &AppModel) -> Option<(String, f64)> {
+    match data.tracking.state {
+        TrackingState::Active(_) if data.config.sound =>
+            Some((data.config.ambient_sound.clone(), data.config.ambient_volume)),
+        _ => None,
+    }
+}
+
+fn start_ambient(data: &AppModel) {
+    match ambient_wanted(data)
+        .and_then(|(name, volume)| Ambient::from_setting(&name, config_dir(data)).map(|a| (a, volume))) {
+        Some((ambient, volume)) => data.audio.start_ambient(ambient, volume as f32),
+        None => data.audio.stop_ambient(),
+    }
+}
+
+/// Loop the ambient sound while work is tracked, stop it on pause, break or
+/// stop, follow changes of its settings. Those come on Save in the settings
+/// window, typing there doesn't restart the stream.
+fn update_ambient(old: &AppModel, data: &AppModel) {
+    match (ambient_wanted(old), ambient_wanted(data)) {
+        (Some((old_name, old_volume)), Some((name, volume))) if old_name == name => {
+            if old_volume != volume {
+                data.audio.set_ambient_volume(volume as f32);
+            }
+        },
+        (None, None) => {},
+        _ => start_ambient(data),
+    }
+}
+
+/// The task's own sound for a finished pomodoro, the configured one if it
+/// has none.
+fn work_finished_sound(data: &AppModel, uid: &String) -> String {
+    match data.tasks.get(uid) {
+        Some(task) if !task.sound.is_empty() => task.sound.clone(),
+        _ => data.config.sound_work_finished.clone(),
+    }
+}
+
+/// Only `sound` is written, whatever else the config file holds stays.
+fn toggle_mute(data: &mut AppModel) {
+    data.config.sound = !data.config.sound;
+
+    let saved = config::load(&data.config_path).and_then(|mut saved| {
+        saved.sound = data.config.sound;
+        config::save(&data.config_path, &saved)
+    });
+
+    if let Err(what) = saved {
+        println!("config error: {}", what);
+    }
+}
+
+/// Desktop notification, unless turned off in the settings. Its buttons
+/// come back as commands while tracking stays as it is now.
+fn notify(data: &AppModel, summary: &str, actions: Vec<Action>) {
+    notification::show(&data.config, data.event_sink.as_ref().map(|sink| (**sink).clone()),
+                       summary, actions, ipc::snapshot(data).status);
+}
+
+/// What can follow a finished pomodoro, given how it went on.
+fn work_finished_actions(data: &AppModel, uid: &String) -> Vec<Action> {
+    match data.tracking.state {
+        TrackingState::Break(_) =>
+            vec![Action::task("Skip break", COMMAND_BREAK_SKIP, uid.clone())],
+        TrackingState::Inactive if data.prompts.iter().any(|p| matches!(p, Prompt::ConfirmBreak(_))) =>
+            vec![Action::task("Start break", COMMAND_BREAK_START, uid.clone()),
+                 Action::task("Skip break", COMMAND_BREAK_SKIP, uid.clone())],
+        TrackingState::Inactive =>
+            vec![Action::task("Start break", COMMAND_BREAK_START, uid.clone()),
+                 Action::task("Start next pomodoro", COMMAND_TASK_START, uid.clone())],
+        _ => vec![],
+    }
+}
+
+/// End the running or paused pomodoro as completed and go on with the break
+/// as configured.
+fn finish_pomodoro(data: &mut AppModel, uid: String, ctx: &mut EventCtx) {
+    stop_session(data, TrackingState::Inactive, SessionOutcome::Completed);
+
+    if data.config.skip_breaks {
+        skip_rest(data, uid, ctx);
+    } else if data.config.confirm_break {
+        data.prompts.push_back(Prompt::ConfirmBreak(uid));
+    } else {
+        start_rest(data, uid, ctx);
+    }
+}
+
+/// (Re)arm the work timer for the time left of the running pomodoro.
+fn arm_work_timer(data: &mut AppModel, ctx: &mut EventCtx) {
+    let remaining = match get_remaining_time(data) {
+        Some(remaining) => remaining,
+        None => return,
+    };
+
+    // flow mode announced the overtime already
+    if data.config.flow_mode && get_overtime(data).is_some() &&
+        *data.tracking.timer_id == TimerToken::INVALID {
+        return;
+    }
+
+    data.tracking.timer_id = Rc::new(ctx.request_timer(
+        remaining.to_std().unwrap_or(std::time::Duration::ZERO)));
+}
+
+fn extend_tracking(data: &mut AppModel, ctx: &mut EventCtx) {
+    let step = chrono::Duration::from_std(TRACKING_EXTEND_STEP).unwrap();
+
+    match data.tracking.state.clone() {
+        TrackingState::Active(uid) if !is_stopwatch(data, &uid) => {
+            data.tracking.extended = Rc::new(*data.tracking.extended + step);
+            arm_work_timer(data, ctx);
+        },
+        // resume arms the timer
+        TrackingState::Paused(uid) if !is_stopwatch(data, &uid) => {
+            data.tracking.extended = Rc::new(*data.tracking.extended + step);
+        },
+        _ => return,
+    }
+
+    save_tracking(data);
+}
+
+/// The running session started earlier than tracked, move its start back
+/// unless it would overlap the previous record.
+fn backdate_tracking(data: &mut AppModel, ctx: &mut EventCtx) {
+    if !matches!(data.tracking.state, TrackingState::Active(_)) {
+        return;
+    }
+
+    let from = *data.tracking.timestamp - chrono::Duration::from_std(TRACKING_BACKDATE_STEP).unwrap();
+
+    // the pause or break just before counts too
+    if let Err(what) = data.check_overlap(&from, &data.tracking.timestamp,
+                                          data.tracking.record_id, None) {
+        println!("backdate error: {}", what);
+        return;
+    }
+
+    if let Some(id) = data.tracking.record_id {
+        if let Err(what) = db::move_open_time_record(data.db.clone(), id, &from) {
+            println!("db error: {}", what);
+            return;
+        }
+    }
+
+    // the first segment of the pomodoro starts the session too
+    if let (Some(id), true) = (data.tracking.session_id, data.tracking.elapsed.is_zero()) {
+        if let Err(what) = db::move_session(data.db.clone(), id, &from) {
+            println!("db error: {}", what);
+        }
+    }
+
+    data.tracking.timestamp = Rc::new(from);
+    arm_work_timer(data, ctx);
+    save_tracking(data);
+}
+
+/// Break prompts answered some other way, from a notification or a key.
+fn drop_break_prompts(data: &mut AppModel) {
+    data.prompts.retain(|p| !matches!(p, Prompt::ConfirmBreak(_)));
+}
+
+/// Go on after a completed pomodoro without a break, a skipped long break
+/// ends the cycle all the same.
+fn skip_rest(data: &mut AppModel, uid: String, ctx: &mut EventCtx) {
+    if is_long_break_due(data) {
+        data.tracking.cycle = 0;
+        save_tracking(data);
+    }
+
+    if can_auto_start(data) {
+        start_next(data, uid, ctx);
+    }
+}
+
+/// Start the next pomodoro of a run without user action.
+fn start_next(data: &mut AppModel, uid: String, ctx: &mut EventCtx) {
+    let auto_run = data.tracking.auto_run;
+
+    start_tracking(data, uid, ctx);
+
+    data.tracking.auto_run = auto_run;
+    save_tracking(data);
+}
+
+fn save_tracking(data: &AppModel) {
+    if let Err(what) = db::save_tracking(data.db.clone(), &data.tracking) {
+        println!("db error: {}", what);
+    }
+}
+
+/// Arm the timer of a session restored from the database on startup.
+fn rearm_tracking(data: &mut AppModel, ctx: &mut EventCtx) {
+    if *data.tracking.timer_id != TimerToken::INVALID {
+        return;
+    }
+
+    if let Some(remaining) = get_remaining_time(data) {
+        // overtime was announced before the restart already
+        if data.config.flow_mode && get_overtime(data).is_some() {
+            return;
+        }
+
+        data.tracking.timer_id = Rc::new(ctx.request_timer(
+            remaining.to_std().unwrap_or(std::time::Duration::ZERO)));
+    }
+}
+
+fn open_record(data: &mut AppModel, uid: &String, kind: RecordKind) {
+    data.tracking.record_id =
+        match db::open_time_record(data.db.clone(), &data.tracking.timestamp, uid, kind,
+                                   data.tracking.session_id) {
+            Ok(id) => Some(id),
+            Err(what) => {
+                println!("db error: {}", what);
+                None
+            }
+        };
+}
+
+fn checkpoint_tracking(data: &AppModel) {
+    if let Some(id) = data.tracking.record_id {
+        if let Err(what) = db::checkpoint_time_record(data.db.clone(), id, &Utc::now()) {
+            println!("db error: {}", what);
+        }
+    }
 }
 
 fn resume_tracking(data: &mut AppModel, uid: String, ctx: &mut EventCtx) {
+    close_record(data);
     data.tracking.timestamp = Rc::new(Utc::now());
-    data.tracking.timer_id =
-        Rc::new(ctx.request_timer(get_work_interval(data, &uid).checked_sub(&data.tracking.elapsed)
-                                  .unwrap_or(chrono::Duration::zero()).to_std().unwrap()));
+    open_record(data, &uid, RecordKind::Work);
+
+    // no timer when resuming in overtime, the interval is over already
+    let remaining = get_work_interval(data, &uid) - *data.tracking.elapsed;
+    data.tracking.timer_id = Rc::new(match remaining.to_std() {
+        Ok(remaining) if !remaining.is_zero() && !is_stopwatch(data, &uid) =>
+            ctx.request_timer(remaining),
+        _ => TimerToken::INVALID,
+    });
     data.tracking.state = TrackingState::Active(uid);
+    save_tracking(data);
 }
 
 fn start_tracking(data: &mut AppModel, uid: String, ctx: &mut EventCtx) {
@@ -470,7 +1083,26 @@ fn start_tracking(data: &mut AppModel, uid: String, ctx: &mut EventCtx) {
 
     data.tracking.timestamp = Rc::new(Utc::now());
     data.tracking.elapsed = Rc::new(chrono::Duration::zero());
-    data.tracking.timer_id = Rc::new(ctx.request_timer(get_work_interval(data, &uid).to_std().unwrap()));
+    data.tracking.extended = Rc::new(chrono::Duration::zero());
+    data.tracking.auto_run = 0;
+
+    // a long pause from work starts a new cycle
+    let idle = last_work_end(&data.records, &data.records_killed)
+        .map_or(true, |end| data.tracking.timestamp.signed_duration_since(end) >
+                chrono::Duration::minutes(data.config.cycle_reset_minutes));
+
+    if idle {
+        data.tracking.cycle = 0;
+    }
+
+    open_session(data, &uid);
+    open_record(data, &uid, RecordKind::Work);
+    data.tracking.timer_id = Rc::new(if is_stopwatch(data, &uid) {
+        TimerToken::INVALID
+    } else {
+        play_event_sound(data, &data.config.sound_session_start);
+        ctx.request_timer(get_work_interval(data, &uid).to_std().unwrap())
+    });
 
     let mut task = data.tasks.get_mut(&uid).expect(&format!("unknown task {}", &uid));
     let needs_update = task.task_status != InProcess;
@@ -491,44 +1123,240 @@ fn start_tracking(data: &mut AppModel, uid: String, ctx: &mut EventCtx) {
     };
 
     data.tracking.state = TrackingState::Active(uid);
+    save_tracking(data);
 }
 
 fn pause_tracking(data: &mut AppModel, uid: String)
 {
-    stop_tracking(data, TrackingState::Paused(uid));
-    data.tracking.timer_id = Rc::new(TimerToken::INVALID);
+    pause_tracking_at(data, uid, Utc::now());
 }
 
-fn stop_tracking(data: &mut AppModel, new_state: TrackingState) {
+fn pause_tracking_at(data: &mut AppModel, uid: String, at: DateTime<Utc>)
+{
+    stop_session_at(data, TrackingState::Paused(uid.clone()), SessionOutcome::Stopped, at);
     data.tracking.timer_id = Rc::new(TimerToken::INVALID);
+    data.tracking.timestamp = Rc::new(at);
+    open_record(data, &uid, RecordKind::Pause);
+    save_tracking(data);
+}
+
+/// The clock jump `gap` was work after all: drop the pause it was cut
+/// into and go on as if the session never stopped.
+fn count_gap_as_work(data: &mut AppModel, gap: &TimeRecord, ctx: &mut EventCtx) {
+    let uid = match data.tracking.state {
+        TrackingState::Paused(ref uid)
+            if uid.eq(&gap.uid) && data.tracking.record_id == Some(gap.id) => uid.clone(),
+        _ => return,
+    };
+
+    if let Err(what) = db::remove_time_record(data.db.clone(), gap) {
+        println!("db error: {}", what);
+    }
+
+    data.tracking.record_id = None;
+    data.tracking.timestamp = gap.from.clone();
+    data.tracking.state = TrackingState::Active(uid.clone());
+    open_record(data, &uid, RecordKind::Work);
+    arm_work_timer(data, ctx);
+    save_tracking(data);
+}
+
+/// Close the record of the current work session, break or pause.
+fn close_record(data: &mut AppModel) {
+    close_record_at(data, Utc::now());
+}
+
+fn close_record_at(data: &mut AppModel, to: DateTime<Utc>) {
+    let (uid, kind) = match &data.tracking.state {
+        TrackingState::Active(uid) => (uid.clone(), RecordKind::Work),
+        TrackingState::Break(uid) => (uid.clone(), RecordKind::Break),
+        TrackingState::Paused(uid) => (uid.clone(), RecordKind::Pause),
+        TrackingState::Inactive => return,
+    };
+
+    // without an open record only work is worth saving, the start of a
+    // break or pause is not known
+    if data.tracking.record_id.is_none() && kind != RecordKind::Work {
+        return;
+    }
+
+    let mut record = TimeRecord{id: data.tracking.record_id.unwrap_or_default(),
+                                from: data.tracking.timestamp.clone(), to: Rc::new(to),
+                                uid, kind, session_id: data.tracking.session_id};
+
+    // the record could not be opened, store it now that it is complete
+    let result = match data.tracking.record_id {
+        Some(_) => db::close_time_record(data.db.clone(), &record),
+        None => db::add_time_record(data.db.clone(), &record).map(|id| record.id = id),
+    };
+
+    if let Err(what) = result {
+        println!("db error: {}", what);
+    }
+
+    data.tracking.record_id = None;
+    data.records.insert(record.id, record.clone());
+
+    if kind == RecordKind::Work {
+        let task = data.tasks.get(&record.uid).unwrap();
+        let duration = record.duration();
+
+        data.tracking.elapsed = Rc::new(*data.tracking.elapsed + duration);
+
+        println!("Task '{}' duration: {}:{}:{}", &task.name,
+                 duration.num_hours(), duration.num_minutes(), duration.num_seconds());
+
+        add_record_to_sum(data.task_sums.get_mut(&task.uid).expect("unknown uid"), &record);
+    }
+}
+
+/// Drop the time the user was away from the work record it was tracked in.
+/// A running record ends where the user left and a new one starts where
+/// they came back. Returns the span that was cut.
+fn cut_idle_time(data: &mut AppModel, idle: &TimeRecord, ctx: &mut EventCtx)
+                 -> Option<(Rc<DateTime<Utc>>, Rc<DateTime<Utc>>)>
+{
+    if data.tracking.record_id == Some(idle.id) {
+        if let TrackingState::Active(ref uid) = data.tracking.state.clone() {
+            close_record_at(data, *idle.from);
+
+            data.tracking.timestamp = idle.to.clone();
+            open_record(data, uid, RecordKind::Work);
+            arm_work_timer(data, ctx);
+            save_tracking(data);
+
+            return Some((idle.from.clone(), idle.to.clone()));
+        }
+    }
+
+    // the session was stopped or paused meanwhile, cut the stored record
+    let record = match data.records.get(&idle.id) {
+        Some(record) if !data.records_killed.contains(&record.id) => record.clone(),
+        _ => return None,
+    };
+
+    let from = std::cmp::max(*idle.from, *record.from);
+    let to = std::cmp::min(*idle.to, *record.to);
+
+    if to <= from {
+        return None;
+    }
+
+    let rest = match split_record(&record, &from, &record.uid) {
+        Some((first, mut second)) => {
+            second.id = match db::split_time_record(data.db.clone(), &first, &second) {
+                Ok(id) => id,
+                Err(what) => {
+                    println!("db error: {}", what);
+                    return None;
+                }
+            };
+
+            data.records.insert(first.id, first);
+            second
+        },
+        None => record.clone(),
+    };
+
+    let result = if to < *rest.to {
+        let rest = TimeRecord{from: Rc::new(to), ..rest};
+        data.records.insert(rest.id, rest.clone());
+        db::update_time_record(data.db.clone(), &rest)
+    } else {
+        data.records.remove(&rest.id);
+        db::remove_time_record(data.db.clone(), &rest)
+    };
+
+    if let Err(what) = result {
+        println!("db error: {}", what);
+    }
+
+    data.rebuild_task_sum(&record.uid);
 
-    let task = match &data.tracking.state {
-        TrackingState::Active(uid) => data.tasks.get(uid).unwrap(),
-        _ => {
-            data.tracking.state = new_state;
+    Some((Rc::new(from), Rc::new(to)))
+}
+
+fn add_idle_record(data: &mut AppModel, mut record: TimeRecord) {
+    record.id = match db::add_time_record(data.db.clone(), &record) {
+        Ok(id) => id,
+        Err(what) => {
+            println!("db error: {}", what);
             return;
         }
     };
 
-    let now = Rc::new(Utc::now());
-    let record = TimeRecord{from: data.tracking.timestamp.clone(), to: now.clone(),
-                            uid: task.uid.clone()};
+    data.records.insert(record.id, record.clone());
+
+    if let Some(sum) = data.task_sums.get_mut(&record.uid) {
+        add_record_to_sum(sum, &record);
+    }
+}
+
+fn open_session(data: &mut AppModel, uid: &String) {
+    data.tracking.session_id =
+        match db::open_session(data.db.clone(), &data.tracking.timestamp, uid) {
+            Ok(id) => Some(id),
+            Err(what) => {
+                println!("db error: {}", what);
+                None
+            }
+        };
+}
+
+fn close_session(data: &mut AppModel, outcome: SessionOutcome) {
+    data.tracking.extended = Rc::new(chrono::Duration::zero());
+
+    let id = match data.tracking.session_id.take() {
+        Some(id) => id,
+        None => return,
+    };
 
-    if let Err(what) = db::add_time_record(data.db.clone(), &record) {
+    if let Err(what) = db::close_session(data.db.clone(), id, &Utc::now(), outcome) {
         println!("db error: {}", what);
     }
 
-    let duration = now.signed_duration_since(data.tracking.timestamp.as_ref().clone());
+    if outcome == SessionOutcome::Completed {
+        data.tracking.cycle += 1;
+        data.tracking.auto_run += 1;
+
+        if let Some(uid) = data.tracking.state.uid() {
+            let count = data.completed_sessions.get(uid).cloned().unwrap_or(0);
+            data.completed_sessions.insert(uid.clone(), count + 1);
+        }
+    }
+}
+
+fn stop_tracking(data: &mut AppModel, new_state: TrackingState) {
+    stop_session(data, new_state, SessionOutcome::Stopped);
+}
+
+/// Stop tracking, the session ends with `outcome` unless it is only paused.
+fn stop_session(data: &mut AppModel, new_state: TrackingState, outcome: SessionOutcome) {
+    stop_session_at(data, new_state, outcome, Utc::now());
+}
+
+/// Stop the session with its current record ending at `at`.
+fn stop_session_at(data: &mut AppModel, new_state: TrackingState, outcome: SessionOutcome,
+                   at: DateTime<Utc>) {
+    data.tracking.timer_id = Rc::new(TimerToken::INVALID);
 
-    data.tracking.elapsed = Rc::new(duration);
+    // ended in overtime, the pomodoro itself was done
+    let outcome = if get_overtime(data).is_some() {SessionOutcome::Completed} else {outcome};
 
-    println!("Task '{}' duration: {}:{}:{}", &task.name,
-             duration.num_hours(), duration.num_minutes(), duration.num_seconds());
+    if let TrackingState::Break(_) = data.tracking.state {
+        if is_long_break_due(data) {
+            data.tracking.cycle = 0;
+        }
+    }
 
-    data.records.insert(*record.from, record.clone());
-    add_record_to_sum(data.task_sums.get_mut(&task.uid).expect("unknown uid"), &record);
+    close_record_at(data, at);
+
+    if !matches!(new_state, TrackingState::Paused(_)) {
+        close_session(data, outcome);
+    }
 
     data.tracking.state = new_state;
+    save_tracking(data);
 }
 
 fn archive_task(model: &mut AppModel, uid: &String) {
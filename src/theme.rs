@@ -0,0 +1,51 @@
+use druid::widget::EnvScope;
+use druid::{theme, Color, Env, FontDescriptor, FontFamily, FontWeight, Widget};
+
+use crate::app_model::AppModel;
+use crate::common::*;
+use crate::config::Config;
+
+/// Put the colours and fonts of the configured theme into `env`.
+/// "dark" is the default, "light" is the only other one.
+pub fn apply(env: &mut Env, config: &Config) {
+    let light = config.theme == "light";
+
+    let pick = |dark: (u8, u8, u8), light_rgb: (u8, u8, u8)| {
+        let (r, g, b) = if light {light_rgb} else {dark};
+        Color::rgb8(r, g, b)
+    };
+
+    env.set(TASK_COLOR_BG,          pick((80, 73, 69),    (213, 196, 161)));
+    env.set(APP_BORDER,             pick((60, 56, 54),    (235, 219, 178)));
+    env.set(TASK_ACTIVE_COLOR_BG,   pick((250, 189, 47),  (215, 153, 33)));
+    env.set(TASK_REST_COLOR_BG,     pick((131, 162, 152), (69, 133, 136)));
+    env.set(TASK_PAUSE_COLOR_BG,    pick((211, 134, 155), (177, 98, 134)));
+    env.set(TASK_OVERTIME_COLOR_BG, pick((254, 128, 25),  (214, 93, 14)));
+    env.set(TASK_FOCUS_BORDER,      pick((124, 111, 100), (146, 131, 116)));
+    env.set(COLOR_ACTIVE,           pick((255, 255, 255), (40, 40, 40)));
+    env.set(DELETING_TASK_BORDER,   pick((204, 36, 29),   (157, 0, 6)));
+    env.set(RESTORED_TASK_BORDER,   pick((184, 187, 38),  (121, 116, 14)));
+
+    env.set(FONT_LOG_DESCR, FontDescriptor::new(FontFamily::MONOSPACE)
+            .with_size(config.font_size));
+    env.set(FONT_CAPTION_DESCR, FontDescriptor::new(FontFamily::SYSTEM_UI)
+            .with_weight(FontWeight::BOLD).with_size(config.font_size + 4.0));
+
+    // druid's own widgets, its default theme is dark already
+    if light {
+        env.set(theme::WINDOW_BACKGROUND_COLOR, Color::rgb8(251, 241, 199));
+        env.set(theme::TEXT_COLOR, Color::rgb8(60, 56, 54));
+        env.set(theme::PLACEHOLDER_COLOR, Color::rgb8(146, 131, 116));
+        env.set(theme::BACKGROUND_LIGHT, Color::rgb8(242, 229, 188));
+        env.set(theme::BACKGROUND_DARK, Color::rgb8(235, 219, 178));
+        env.set(theme::BUTTON_LIGHT, Color::rgb8(235, 219, 178));
+        env.set(theme::BUTTON_DARK, Color::rgb8(213, 196, 161));
+        env.set(theme::BORDER_LIGHT, Color::rgb8(168, 153, 132));
+        env.set(theme::BORDER_DARK, Color::rgb8(189, 174, 147));
+    }
+}
+
+/// Root of a window, follows theme changes made in the settings.
+pub fn themed(inner: impl Widget<AppModel> + 'static) -> impl Widget<AppModel> {
+    EnvScope::new(|env: &mut Env, data: &AppModel| apply(env, &data.config), inner)
+}
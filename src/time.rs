@@ -83,6 +83,57 @@ impl Add for AggregateDuration {
     }
 }
 
+/// Parse a local "YYYY-MM-DD HH:MM" date and time, a bare "HH:MM" is taken
+/// on the day of `base`.
+pub fn parse_local_time(src: &str, base: &DateTime<Local>) -> Option<DateTime<Utc>>
+{
+    let src = src.trim();
+
+    let naive = NaiveDateTime::parse_from_str(src, "%Y-%m-%d %H:%M").ok()
+        .or_else(|| NaiveTime::parse_from_str(src, "%H:%M").ok()
+                 .map(|t| base.date().naive_local().and_time(t)))?;
+
+    Local.from_local_datetime(&naive).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+/// Parse a duration given as minutes ("90"), "1:30" or "1h30m".
+pub fn parse_duration(src: &str) -> Option<Duration>
+{
+    let src = src.trim();
+
+    if let Ok(minutes) = src.parse::<i64>() {
+        return Some(Duration::minutes(minutes));
+    }
+
+    if let Some((h, m)) = src.split_once(':') {
+        return Some(Duration::hours(h.parse().ok()?) + Duration::minutes(m.parse().ok()?));
+    }
+
+    let mut result = Duration::zero();
+    let mut number = String::new();
+
+    for c in src.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' => result = result + Duration::hours(number.parse().ok()?),
+            'm' => result = result + Duration::minutes(number.parse().ok()?),
+            's' => result = result + Duration::seconds(number.parse().ok()?),
+            ' ' => continue,
+            _ => return None,
+        }
+
+        if !c.is_ascii_digit() {
+            number.clear();
+        }
+    }
+
+    if !number.is_empty() || src.is_empty() {
+        return None;
+    }
+
+    Some(result)
+}
+
 pub fn daystart(src: DateTime<Local>) -> DateTime<Utc>
 {
     DateTime::<Utc>::from(src.with_hour(0).unwrap()
@@ -126,3 +177,85 @@ pub fn get_durations(task_sums: &TaskSums) -> AggregateDuration {
 
     return result;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Local> {
+        Local.ymd(y, mo, d).and_hms(h, mi, 0)
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration(" 1:30 "), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1h 30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_duration("1m30s"), Some(Duration::seconds(90)));
+    }
+
+    #[test]
+    fn malformed_durations() {
+        for src in ["", " ", "abc", "1x", "1h30", "h", "1:", ":30", "1:xx", "1.5h"] {
+            assert_eq!(parse_duration(src), None, "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn local_times() {
+        let base = local(2022, 1, 31, 12, 0);
+
+        assert_eq!(parse_local_time("2022-01-30 09:15", &base),
+                   Some(local(2022, 1, 30, 9, 15).with_timezone(&Utc)));
+        assert_eq!(parse_local_time(" 09:15 ", &base),
+                   Some(local(2022, 1, 31, 9, 15).with_timezone(&Utc)));
+    }
+
+    #[test]
+    fn malformed_local_times() {
+        let base = local(2022, 1, 31, 12, 0);
+
+        for src in ["", "9", "25:00", "09:60", "2022-13-01 09:00", "2022-01-31", "noon"] {
+            assert_eq!(parse_local_time(src, &base), None, "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn times_past_midnight() {
+        let base = local(2022, 1, 31, 23, 30);
+
+        // a bare time stays on the day of `base`, the next day needs the date
+        assert_eq!(parse_local_time("00:30", &base),
+                   Some(local(2022, 1, 31, 0, 30).with_timezone(&Utc)));
+        assert_eq!(parse_local_time("2022-02-01 00:30", &base),
+                   Some(local(2022, 2, 1, 0, 30).with_timezone(&Utc)));
+
+        let end = base.with_timezone(&Utc) + parse_duration("1h").unwrap();
+        assert_eq!(end, local(2022, 2, 1, 0, 30).with_timezone(&Utc));
+    }
+
+    #[test]
+    fn older_records_add_to_later_sums() {
+        let at = |h, m| local(2022, 1, 31, h, m).with_timezone(&Utc);
+        let record = |from, to| TimeRecord{id: 0, from: Rc::new(from), to: Rc::new(to),
+                                           uid: "a".to_string(), kind: RecordKind::Work,
+                                           session_id: None};
+        let end = at(23, 0);
+
+        let mut sum = TimePrefixSum::new();
+        add_record_to_sum(&mut sum, &record(at(10, 0), at(10, 30)));
+        add_record_to_sum(&mut sum, &record(at(9, 0), at(9, 20)));
+
+        assert_eq!(get_total_time(&sum, &at(0, 0), &end), Duration::minutes(50));
+        assert_eq!(get_total_time(&sum, &at(9, 30), &end), Duration::minutes(30));
+
+        // breaks don't count as work
+        add_record_to_sum(&mut sum, &TimeRecord{kind: RecordKind::Break,
+                                                ..record(at(8, 0), at(8, 30))});
+        assert_eq!(get_total_time(&sum, &at(0, 0), &end), Duration::minutes(50));
+    }
+}
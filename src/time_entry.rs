@@ -0,0 +1,175 @@
+use std::rc::Rc;
+
+use druid::im::Vector;
+use druid::lens::{self, LensExt};
+use druid::widget::{Button, CrossAxisAlignment, Flex, Label, List, Painter, Scroll, TextBox};
+use druid::{commands, PaintCtx, RenderContext, UnitPoint, Widget, WidgetExt, WindowDesc,
+            LocalizedString};
+
+use chrono::prelude::*;
+
+use crate::task::*;
+use crate::app_model::*;
+use crate::common::*;
+use crate::db;
+use crate::theme;
+
+/// Draft for a new entry: selected task, started an hour ago, lasting an hour.
+pub fn new_time_entry_draft(model: &AppModel) -> TimeEntryDraft {
+    let start: DateTime<Local> = Local::now() - chrono::Duration::hours(1);
+
+    TimeEntryDraft {
+        uid: model.selected_task.clone(),
+        start: start.format("%Y-%m-%d %H:%M").to_string(),
+        end: "".to_string(),
+        duration: "60".to_string(),
+        error: "".to_string(),
+    }
+}
+
+pub fn time_entry_window() -> WindowDesc<AppModel> {
+    WindowDesc::new(theme::themed(time_entry_widget()))
+        .window_size((450.0, 500.0))
+        .title(LocalizedString::new("netupi-time-entry-title").with_placeholder("Add time entry"))
+}
+
+fn validate_time_entry(model: &AppModel) -> Result<TimeRecord, String> {
+    let draft = &model.time_entry;
+    let now = Utc::now();
+
+    let uid = match draft.uid {
+        Some(ref uid) if model.tasks.contains_key(uid) => uid.clone(),
+        _ => return Err("Select a task".to_string()),
+    };
+
+    let from = crate::time::parse_local_time(&draft.start, &Local::now())
+        .ok_or("Start should look like 2022-01-31 09:00 or 09:00")?;
+
+    let to = if !draft.end.trim().is_empty() {
+        crate::time::parse_local_time(&draft.end, &DateTime::<Local>::from(from))
+            .ok_or("End should look like 2022-01-31 10:00 or 10:00")?
+    } else {
+        from + crate::time::parse_duration(&draft.duration)
+            .ok_or("Duration should look like 90, 1:30 or 1h30m")?
+    };
+
+    if to <= from {
+        return Err("Entry should end after it starts".to_string());
+    }
+
+    if to > now {
+        return Err("Entry should not end in the future".to_string());
+    }
+
+    model.check_overlap(&from, &to, None, Some(RecordKind::Work))?;
+
+    Ok(TimeRecord{id: 0, from: Rc::new(from), to: Rc::new(to), uid, kind: RecordKind::Work,
+                  session_id: None})
+}
+
+fn add_time_entry(model: &mut AppModel, mut record: TimeRecord) {
+    record.id = match db::add_time_record(model.db.clone(), &record) {
+        Ok(id) => id,
+        Err(what) => {
+            println!("db error: {}", what);
+            model.time_entry.error = format!("db error: {}", what);
+            return;
+        }
+    };
+
+    model.records.insert(record.id, record.clone());
+    add_record_to_sum(model.task_sums.get_mut(&record.uid).expect("unknown uid"), &record);
+}
+
+fn caption(text: &str) -> impl Widget<AppModel> {
+    Label::new(text).with_font(FONT_CAPTION_DESCR)
+}
+
+fn task_select_widget() -> impl Widget<AppModel> {
+    Scroll::new(
+        List::new(|| {
+            Label::new(|(model, uid): &(AppModel, String), _env: &_|
+                       model.tasks.get(uid).map(|t| t.name.clone()).unwrap_or_default())
+                .align_vertical(UnitPoint::LEFT)
+                .padding(10.0)
+                .expand_width()
+                .background(
+                    Painter::new(|ctx: &mut PaintCtx, (model, uid): &(AppModel, String), env| {
+                        let bounds = ctx.size().to_rect();
+                        if model.time_entry.uid.as_ref() == Some(uid) {
+                            ctx.fill(bounds, &env.get(TASK_COLOR_BG));
+                        } else {
+                            ctx.stroke(bounds, &env.get(TASK_COLOR_BG), 2.0);
+                        }
+                    }))
+                .on_click(|_ctx, (model, uid): &mut (AppModel, String), _env| {
+                    model.time_entry.uid = Some(uid.clone());
+                })
+        })
+        .with_spacing(10.0)
+        .lens(lens::Identity.map(
+            |d: &AppModel| (d.clone(), d.get_tasks_filtered().into_iter()
+                                         .filter(|t| t.task_status != TaskStatus::Archived)
+                                         .map(|t| t.uid).collect::<Vector<String>>()),
+            |d: &mut AppModel, x: (AppModel, Vector<String>)| *d = x.0,
+        )))
+    .vertical()
+}
+
+fn time_entry_widget() -> impl Widget<AppModel> {
+    let draft = AppModel::time_entry;
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(caption("Task"))
+        .with_default_spacer()
+        .with_flex_child(task_select_widget(), 1.0)
+        .with_spacer(15.0)
+        .with_child(
+            Flex::row()
+                .with_child(caption("Start").fix_width(100.0))
+                .with_flex_child(TextBox::new()
+                                 .with_placeholder("2022-01-31 09:00")
+                                 .expand_width()
+                                 .lens(draft.then(TimeEntryDraft::start)), 1.0))
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(caption("End").fix_width(100.0))
+                .with_flex_child(TextBox::new()
+                                 .with_placeholder("10:30")
+                                 .expand_width()
+                                 .lens(draft.then(TimeEntryDraft::end)), 1.0))
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(caption("or duration").fix_width(100.0))
+                .with_flex_child(TextBox::new()
+                                 .with_placeholder("1h30m")
+                                 .expand_width()
+                                 .lens(draft.then(TimeEntryDraft::duration)), 1.0))
+        .with_spacer(15.0)
+        .with_child(Label::new(|d: &AppModel, _env: &_| d.time_entry.error.clone())
+                    .with_text_color(DELETING_TASK_BORDER))
+        .with_default_spacer()
+        .with_child(
+            Flex::row()
+                .with_child(Button::new("Add")
+                            .on_click(|ctx, model: &mut AppModel, _env| {
+                                match validate_time_entry(model) {
+                                    Ok(record) => {
+                                        add_time_entry(model, record);
+                                        if model.time_entry.error.is_empty() {
+                                            ctx.submit_command(commands::CLOSE_WINDOW);
+                                        }
+                                    },
+                                    Err(what) => model.time_entry.error = what,
+                                }
+                            }))
+                .with_default_spacer()
+                .with_child(Button::new("Cancel")
+                            .on_click(|ctx, _model: &mut AppModel, _env| {
+                                ctx.submit_command(commands::CLOSE_WINDOW);
+                            })))
+        .padding(15.0)
+}
@@ -24,18 +24,18 @@ pub fn duration_widget() -> impl Widget<Rc<time::AggregateDuration>> {
         result.push_str(&format!("{:>12}", time::format_duration(&duration.total)));
 
         return result;
-    }).with_font(FONT_LOG_DESCR.clone());
+    }).with_font(FONT_LOG_DESCR);
 
     Flex::row()
         .with_child(Label::new("Today\nWeek\nMonth\nYear\nAll time")
-                    .with_font(FONT_LOG_DESCR.clone()))
+                    .with_font(FONT_LOG_DESCR))
         .with_default_spacer()
         .with_child(label)
         .padding(10.0)
         .background(
-            Painter::new(|ctx: &mut PaintCtx, _item: &_, _env| {
+            Painter::new(|ctx: &mut PaintCtx, _item: &_, env| {
                 let bounds = ctx.size().to_rect();
-                ctx.stroke(bounds, &TASK_COLOR_BG, 2.0);
+                ctx.stroke(bounds, &env.get(TASK_COLOR_BG), 2.0);
             }))
 }
 